@@ -0,0 +1,48 @@
+use std::{env, error::Error, fs, path::{Path, PathBuf}};
+use serde::Deserialize;
+
+// Settings that can be kept in a TOML file instead of being passed on every run.
+// Keys are named after the matching command line flags, which take precedence over the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub api_endpoint: Option<String>,
+    pub user_agent: Option<String>,
+    pub timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub proxy: Option<String>,
+    pub requests_per_second: Option<u32>,
+    pub cache_dir: Option<PathBuf>,
+}
+
+// Follows the XDG base directory layout, falling back to the temporary directory without a home directory.
+fn user_directory(xdg_variable: &str, home_subdirectory: &str) -> PathBuf {
+    let base_directory = env::var_os(xdg_variable)
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(home_subdirectory)))
+        .unwrap_or_else(env::temp_dir);
+
+    base_directory.join(env!("CARGO_PKG_NAME"))
+}
+
+pub fn default_cache_dir() -> PathBuf {
+    user_directory("XDG_CACHE_HOME", ".cache")
+}
+
+pub fn default_config_path() -> PathBuf {
+    user_directory("XDG_CONFIG_HOME", ".config").join("config.toml")
+}
+
+// A config file given explicitly has to exist, the default one is optional.
+pub fn load_config(config_path: Option<&Path>) -> Result<Config, Box<dyn Error>> {
+    let config_data = match config_path {
+        Some(config_path) => fs::read_to_string(config_path)?,
+        None => match fs::read_to_string(default_config_path()) {
+            Ok(config_data) => config_data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(Box::new(error)),
+        },
+    };
+
+    Ok(toml::from_str(&config_data)?)
+}
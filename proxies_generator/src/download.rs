@@ -0,0 +1,150 @@
+use std::{collections::HashMap, error::Error, fs, path::Path};
+use log::info;
+use scryfall::csv;
+
+use crate::{image_download::ImageLoader, page_layout::NamedProxyCard, zip_archive::{read_stored_files, ZipWriter}};
+
+const MANIFEST_FILE_NAME: &str = "manifest.csv";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DownloadFile {
+    name: String,
+    source: String,
+}
+
+// Scryfall image URLs end in a timestamp query, which is not part of the extension.
+fn file_extension(source: &str) -> &str {
+    let path = source.split(['?', '#']).next().unwrap_or(source);
+
+    path.rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.is_empty() && !extension.contains(['/', '\\']))
+        .unwrap_or("jpg")
+}
+
+fn faces(card: &NamedProxyCard) -> impl Iterator<Item = (&'static str, &String)> {
+    [("front", Some(&card.front)), ("back", card.back.as_ref())].into_iter()
+        .filter_map(|(face, proxy_image)| Some((face, &proxy_image?.source)))
+}
+
+// Copies after the first are numbered from 2, so cards with a single copy keep the plain name.
+fn planned_files(cards: &[NamedProxyCard], repeat_copies: bool) -> Vec<DownloadFile> {
+    let mut files = Vec::new();
+
+    for card in cards {
+        let copies = if repeat_copies { card.count } else { 1 };
+
+        for (face, source) in faces(card) {
+            for copy in 1..=copies {
+                let copy_suffix = if copy == 1 { String::new() } else { format!("-{copy}") };
+                files.push(DownloadFile {
+                    name: format!("{}-{face}{copy_suffix}.{}", card.file_stem, file_extension(source)),
+                    source: source.clone(),
+                });
+            }
+        }
+    }
+
+    files
+}
+
+fn manifest(cards: &[NamedProxyCard]) -> String {
+    let mut manifest = String::from("File,Count,Name\n");
+
+    for card in cards {
+        for (face, source) in faces(card) {
+            let file_name = format!("{}-{face}.{}", card.file_stem, file_extension(source));
+            manifest += &format!("{},{},{}\n", csv::escape(&file_name, ','), card.count, csv::escape(&card.name, ','));
+        }
+    }
+
+    manifest
+}
+
+// Files from an earlier download are kept if they still decode, a partly written image does not.
+fn existing_files(output: &Path, files: &[DownloadFile], is_zip: bool) -> HashMap<String, Vec<u8>> {
+    let existing_files: Vec<(String, Vec<u8>)> = if is_zip {
+        fs::read(output).map(|archive| read_stored_files(&archive)).unwrap_or_default()
+    } else {
+        files.iter()
+            .filter_map(|file| Some((file.name.clone(), fs::read(output.join(&file.name)).ok()?)))
+            .collect()
+    };
+
+    existing_files.into_iter()
+        .filter(|(name, data)| files.iter().any(|file| file.name == *name) && image::load_from_memory(data).is_ok())
+        .inspect(|(name, _)| info!("Skipping {name}, it was already downloaded"))
+        .collect()
+}
+
+// Writes the images of each card into a folder, or a ZIP archive when the output ends in .zip.
+// Without repeated copies each image is written once, with a manifest listing how many copies of each card there are.
+pub async fn download_images(output: &Path, cards: &[NamedProxyCard], repeat_copies: bool, image_loader: &ImageLoader) -> Result<(), Box<dyn Error>> {
+    let is_zip = output.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    let files = planned_files(cards, repeat_copies);
+    let existing_files = existing_files(output, &files, is_zip);
+
+    let missing_sources = files.iter()
+        .filter(|file| !existing_files.contains_key(&file.name))
+        .map(|file| &file.source);
+    let loaded_images = image_loader.load_images(missing_sources).await?;
+
+    let file_contents = |file: &DownloadFile| existing_files.get(&file.name)
+        .or_else(|| loaded_images.get(&file.source))
+        .ok_or("Card image was not loaded");
+    let manifest = (!repeat_copies).then(|| manifest(cards));
+
+    if is_zip {
+        let mut archive = ZipWriter::default();
+        for file in &files {
+            archive.add_file(&file.name, file_contents(file)?)?;
+        }
+        if let Some(manifest) = &manifest {
+            archive.add_file(MANIFEST_FILE_NAME, manifest.as_bytes())?;
+        }
+
+        info!("Writing {}", output.display());
+        fs::write(output, archive.finish()?)?;
+    } else {
+        fs::create_dir_all(output)?;
+        for file in files.iter().filter(|file| !existing_files.contains_key(&file.name)) {
+            info!("Writing {}", file.name);
+            fs::write(output.join(&file.name), file_contents(file)?)?;
+        }
+        if let Some(manifest) = &manifest {
+            fs::write(output.join(MANIFEST_FILE_NAME), manifest)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page_layout::ProxyImage;
+
+    fn named_card(name: &str, file_stem: &str, sources: &[&str], count: usize) -> NamedProxyCard {
+        let proxy_image = |source: &&str| ProxyImage { source: source.to_string(), border_rgb: [0, 0, 0] };
+        NamedProxyCard { name: name.to_owned(), file_stem: file_stem.to_owned(), front: proxy_image(&sources[0]), back: sources.get(1).map(proxy_image), count }
+    }
+
+    #[test]
+    fn test_download_file_names() {
+        let cards = [
+            named_card("Opt", "xln-65", &["https://cards.scryfall.io/large/front/0/1/01.jpg?1562"], 2),
+            named_card("Delver of Secrets // Insectile Aberration", "isd-51", &["https://cards.scryfall.io/png/front/0/2/02.png?1", "https://cards.scryfall.io/png/back/0/2/02.png?1"], 1),
+            named_card("Custom card", "custom-1", &["/home/user/my card"], 1),
+        ];
+
+        let names = |files: Vec<DownloadFile>| files.into_iter().map(|file| file.name).collect::<Vec<_>>();
+        assert_eq!(names(planned_files(&cards, true)), vec!["xln-65-front.jpg", "xln-65-front-2.jpg", "isd-51-front.png", "isd-51-back.png", "custom-1-front.jpg"]);
+        assert_eq!(names(planned_files(&cards, false)), vec!["xln-65-front.jpg", "isd-51-front.png", "isd-51-back.png", "custom-1-front.jpg"]);
+
+        assert_eq!(manifest(&cards), "File,Count,Name\n\
+xln-65-front.jpg,2,Opt\n\
+isd-51-front.png,1,Delver of Secrets // Insectile Aberration\n\
+isd-51-back.png,1,Delver of Secrets // Insectile Aberration\n\
+custom-1-front.jpg,1,Custom card\n");
+    }
+}
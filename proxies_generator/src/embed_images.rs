@@ -0,0 +1,151 @@
+use std::{collections::HashMap, error::Error, fs, path::Path};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use image::ImageFormat;
+use log::info;
+
+use crate::page_layout::ProxyImage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmbedImages {
+    /// Inline each image into the HTML file once as a data URI, which every copy of the card shares.
+    Inline,
+    /// Write the images into a folder next to the HTML file.
+    Assets,
+}
+
+// Each unique image once, in the order it first appears, with the position of each source.
+#[derive(Debug, Clone, Default)]
+pub struct InlineImages {
+    pub data_uris: Vec<String>,
+    pub indices: HashMap<String, usize>,
+}
+
+fn unique_sources(proxy_images: &[ProxyImage]) -> Vec<&String> {
+    let mut sources: Vec<&String> = Vec::new();
+
+    for proxy_image in proxy_images {
+        if !sources.contains(&&proxy_image.source) {
+            sources.push(&proxy_image.source);
+        }
+    }
+
+    sources
+}
+
+// The MIME type and extension of an image, text proxies being SVG which the image crate does not read.
+fn image_type(data: &[u8]) -> (&'static str, &'static str) {
+    if data.starts_with(b"<svg") {
+        return ("image/svg+xml", "svg");
+    }
+
+    let format = image::guess_format(data).unwrap_or(ImageFormat::Jpeg);
+    (format.to_mime_type(), format.extensions_str().first().copied().unwrap_or("jpg"))
+}
+
+// The images are given already loaded, so processed images can be embedded instead of the downloaded ones.
+pub fn inline_images(proxy_images: &[ProxyImage], loaded_images: &HashMap<String, Vec<u8>>) -> Result<InlineImages, Box<dyn Error>> {
+    let sources = unique_sources(proxy_images);
+    let mut inline_images = InlineImages::default();
+
+    for source in sources {
+        let data = loaded_images.get(source).ok_or("Card image was not loaded")?;
+        let (mime_type, _) = image_type(data);
+
+        inline_images.indices.insert(source.clone(), inline_images.data_uris.len());
+        inline_images.data_uris.push(format!("data:{mime_type};base64,{}", STANDARD.encode(data)));
+    }
+
+    Ok(inline_images)
+}
+
+// Only the characters that would end the path or the attribute are encoded, as the folder is named after a local file.
+fn url_path_segment(segment: &str) -> String {
+    segment.chars().map(|character| match character {
+        '%' | ' ' | '"' | '#' | '?' | '<' | '>' => format!("%{:02X}", character as u32),
+        _ => character.to_string(),
+    }).collect()
+}
+
+// The folder is named like the ones browsers save pages with, and the images are linked relative to the HTML file.
+pub fn write_image_assets(proxy_images: &[ProxyImage], loaded_images: &HashMap<String, Vec<u8>>, html_path: &Path) -> Result<Vec<ProxyImage>, Box<dyn Error>> {
+    let file_stem = html_path.file_stem().map(|file_stem| file_stem.to_string_lossy()).unwrap_or_default();
+    let folder_name = format!("{file_stem}_files");
+    let folder = html_path.with_file_name(&folder_name);
+    fs::create_dir_all(&folder)?;
+
+    let sources = unique_sources(proxy_images);
+    let mut asset_paths: HashMap<&String, String> = HashMap::new();
+
+    for (index, source) in sources.into_iter().enumerate() {
+        let data = loaded_images.get(source).ok_or("Card image was not loaded")?;
+        let (_, extension) = image_type(data);
+        let file_name = format!("card-{}.{extension}", index + 1);
+
+        info!("Writing image asset {file_name}");
+        fs::write(folder.join(&file_name), data)?;
+        asset_paths.insert(source, format!("{}/{file_name}", url_path_segment(&folder_name)));
+    }
+
+    Ok(proxy_images.iter().map(|proxy_image| ProxyImage {
+        source: asset_paths[&proxy_image.source].clone(),
+        border_rgb: proxy_image.border_rgb,
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy_images(sources: &[&str]) -> Vec<ProxyImage> {
+        sources.iter().map(|source| ProxyImage { source: source.to_string(), border_rgb: [0, 0, 0] }).collect()
+    }
+
+    fn loaded_images() -> HashMap<String, Vec<u8>> {
+        HashMap::from([
+            ("opt.png".to_owned(), b"\x89PNG\r\n\x1a\nopt".to_vec()),
+            ("bolt.svg".to_owned(), b"<svg>bolt</svg>".to_vec()),
+        ])
+    }
+
+    #[test]
+    fn test_inline_images_once_per_source() {
+        let deck = proxy_images(&["opt.png", "bolt.svg", "opt.png", "opt.png", "bolt.svg", "opt.png"]);
+        let inlined = inline_images(&deck, &loaded_images()).expect("Every image should be loaded");
+
+        assert_eq!(inlined.data_uris, vec![
+            format!("data:image/png;base64,{}", STANDARD.encode(b"\x89PNG\r\n\x1a\nopt")),
+            format!("data:image/svg+xml;base64,{}", STANDARD.encode(b"<svg>bolt</svg>")),
+        ]);
+        assert_eq!(inlined.indices, HashMap::from([("opt.png".to_owned(), 0), ("bolt.svg".to_owned(), 1)]));
+
+        assert!(inline_images(&proxy_images(&["missing.png"]), &loaded_images()).is_err());
+    }
+
+    #[test]
+    fn test_write_image_assets() {
+        let directory = std::env::temp_dir().join(format!("proxies_assets_test_{}", std::process::id()));
+        let html_path = directory.join("my deck.html");
+
+        let deck = proxy_images(&["opt.png", "bolt.svg", "opt.png", "opt.png"]);
+        let asset_images = write_image_assets(&deck, &loaded_images(), &html_path).expect("Images should be written");
+
+        let sources: Vec<&str> = asset_images.iter().map(|proxy_image| proxy_image.source.as_str()).collect();
+        assert_eq!(sources, vec!["my%20deck_files/card-1.png", "my%20deck_files/card-2.svg", "my%20deck_files/card-1.png", "my%20deck_files/card-1.png"]);
+
+        let mut file_names: Vec<String> = fs::read_dir(directory.join("my deck_files")).expect("Asset folder should exist")
+            .map(|entry| entry.expect("Asset should be readable").file_name().to_string_lossy().into_owned())
+            .collect();
+        file_names.sort();
+        assert_eq!(file_names, vec!["card-1.png", "card-2.svg"]);
+        assert_eq!(fs::read(directory.join("my deck_files/card-2.svg")).expect("Asset should be readable"), b"<svg>bolt</svg>");
+
+        fs::remove_dir_all(&directory).expect("Test directory should be removable");
+    }
+
+    #[test]
+    fn test_url_path_segment() {
+        assert_eq!(url_path_segment("my deck_files"), "my%20deck_files");
+        assert_eq!(url_path_segment("100% \"aggro\"_files"), "100%25%20%22aggro%22_files");
+    }
+}
@@ -0,0 +1,50 @@
+use std::{collections::HashMap, error::Error};
+use futures::{stream, StreamExt, TryStreamExt};
+use log::info;
+use reqwest::Client;
+use scryfall::api_interface::{client_settings::ClientSettings, reqwest_wrapper::build_http_client};
+
+async fn load_image(client: &Client, source: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        info!("Downloading image {source}");
+
+        let response = client.get(source)
+            .send().await?
+            .error_for_status()?
+            .bytes().await?;
+
+        Ok(response.to_vec())
+    } else {
+        info!("Reading image {source}");
+
+        Ok(tokio::fs::read(source).await?)
+    }
+}
+
+// Images are downloaded with the same proxy, user agent and timeouts as API requests.
+// The image CDN has no rate limit of its own, so the API's requests per second only bound how many are downloaded at once.
+pub struct ImageLoader {
+    client: Client,
+    concurrent_downloads: usize,
+}
+
+impl ImageLoader {
+    pub fn new(settings: &ClientSettings) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            client: build_http_client(settings)?,
+            concurrent_downloads: settings.requests_per_second.max(1) as usize,
+        })
+    }
+
+    pub async fn load_images<'a>(&self, sources: impl IntoIterator<Item = &'a String>) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
+        let mut unique_sources: Vec<&String> = sources.into_iter().collect();
+        unique_sources.sort();
+        unique_sources.dedup();
+
+        stream::iter(unique_sources)
+            .map(|source| async move { Ok::<_, Box<dyn Error>>((source.clone(), load_image(&self.client, source).await?)) })
+            .buffer_unordered(self.concurrent_downloads)
+            .try_collect()
+            .await
+    }
+}
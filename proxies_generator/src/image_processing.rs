@@ -0,0 +1,165 @@
+use std::{collections::HashMap, error::Error, io::Cursor};
+use image::{codecs::jpeg::JpegEncoder, imageops::{self, FilterType}, DynamicImage, ImageFormat, Rgba, RgbaImage};
+use log::info;
+
+use crate::{image_download::ImageLoader, page_layout::{ProxyImage, CARD_HEIGHT_MM, CARD_WIDTH_MM}};
+
+const MM_PER_INCH: f32 = 25.4;
+const JPEG_QUALITY: u8 = 95;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageTransform {
+    // Scryfall's PNG images have transparent rounded corners, which print as white slivers.
+    FillCorners,
+    // Repeats the outermost pixels outwards, so borderless art continues into the bleed as well as borders do.
+    ExtendBorder { bleed_mm: f32 },
+    Resample { dpi: f32 },
+    Grayscale,
+}
+
+// The image along with the physical size it is printed at, which grows as bleed is added.
+struct CardImage {
+    pixels: RgbaImage,
+    width_mm: f32,
+    height_mm: f32,
+}
+
+impl CardImage {
+    fn apply(self, transform: ImageTransform, border_rgb: [u8; 3]) -> Self {
+        match transform {
+            ImageTransform::FillCorners => {
+                let [red, green, blue] = border_rgb;
+                let mut filled = RgbaImage::from_pixel(self.pixels.width(), self.pixels.height(), Rgba([red, green, blue, 255]));
+                imageops::overlay(&mut filled, &self.pixels, 0, 0);
+
+                Self { pixels: filled, ..self }
+            },
+            ImageTransform::ExtendBorder { bleed_mm } => {
+                let (width, height) = self.pixels.dimensions();
+                let bleed = (bleed_mm * width as f32 / self.width_mm).round() as u32;
+
+                let extended = RgbaImage::from_fn(width + 2 * bleed, height + 2 * bleed, |x, y| {
+                    *self.pixels.get_pixel(x.saturating_sub(bleed).min(width - 1), y.saturating_sub(bleed).min(height - 1))
+                });
+
+                Self { pixels: extended, width_mm: self.width_mm + 2.0 * bleed_mm, height_mm: self.height_mm + 2.0 * bleed_mm }
+            },
+            ImageTransform::Resample { dpi } => {
+                let width = (self.width_mm / MM_PER_INCH * dpi).round() as u32;
+                let height = (self.height_mm / MM_PER_INCH * dpi).round() as u32;
+
+                if self.pixels.dimensions() == (width, height) {
+                    return self;
+                }
+                Self { pixels: imageops::resize(&self.pixels, width, height, FilterType::Lanczos3), ..self }
+            },
+            ImageTransform::Grayscale => Self { pixels: DynamicImage::ImageRgba8(self.pixels).grayscale().to_rgba8(), ..self },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImagePipeline {
+    pub transforms: Vec<ImageTransform>,
+}
+
+impl ImagePipeline {
+    // The transforms for printing: corners are filled before the border is extended, so the bleed has no gaps at the corners.
+    // Resampling comes before the bleed, so the bleed is measured at the final resolution.
+    pub fn for_print(dpi: Option<f32>, bleed_mm: f32, grayscale: bool) -> Self {
+        let transforms = [
+            Some(ImageTransform::FillCorners),
+            dpi.map(|dpi| ImageTransform::Resample { dpi }),
+            (bleed_mm > 0.0).then_some(ImageTransform::ExtendBorder { bleed_mm }),
+            grayscale.then_some(ImageTransform::Grayscale),
+        ];
+
+        Self { transforms: transforms.into_iter().flatten().collect() }
+    }
+
+    // Whether processed images cover the bleed around the card, instead of only the card.
+    pub fn includes_bleed(&self) -> bool {
+        self.transforms.iter().any(|transform| matches!(transform, ImageTransform::ExtendBorder { .. }))
+    }
+
+    // Card images are taken to be the size of a card, before any bleed is added.
+    pub fn apply(&self, image: DynamicImage, border_rgb: [u8; 3]) -> RgbaImage {
+        let card_image = CardImage { pixels: image.to_rgba8(), width_mm: CARD_WIDTH_MM, height_mm: CARD_HEIGHT_MM };
+
+        self.transforms.iter()
+            .fold(card_image, |card_image, transform| card_image.apply(*transform, border_rgb))
+            .pixels
+    }
+
+    // JPEG is smallest for photos such as card art, PNG keeps every pixel for print shops.
+    pub fn process(&self, data: &[u8], border_rgb: [u8; 3], format: ImageFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+        let processed = DynamicImage::ImageRgba8(self.apply(image::load_from_memory(data)?, border_rgb));
+        let mut encoded = Cursor::new(Vec::new());
+
+        if format == ImageFormat::Jpeg {
+            JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY).encode_image(&processed.to_rgb8())?;
+        } else {
+            processed.write_to(&mut encoded, format)?;
+        }
+
+        Ok(encoded.into_inner())
+    }
+
+    // Downloads each unique image once and processes it, keyed by its source like load_images.
+    pub async fn load_processed_images<'a>(&self, proxy_images: impl IntoIterator<Item = &'a ProxyImage>, format: ImageFormat, image_loader: &ImageLoader) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
+        let mut border_colours: HashMap<&String, [u8; 3]> = HashMap::new();
+        for proxy_image in proxy_images {
+            border_colours.entry(&proxy_image.source).or_insert(proxy_image.border_rgb);
+        }
+
+        let loaded_images = image_loader.load_images(border_colours.keys().copied()).await?;
+
+        loaded_images.into_iter().map(|(source, data)| {
+            info!("Processing image {source}");
+            let processed = self.process(&data, border_colours[&source], format)?;
+            Ok((source, processed))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A white card at ten pixels per millimetre, with a transparent top left corner as Scryfall's PNG images have.
+    fn card_with_transparent_corner() -> DynamicImage {
+        let mut card_image = RgbaImage::from_pixel(630, 880, Rgba([255, 255, 255, 255]));
+        for (x, y) in [(0, 0), (1, 0), (0, 1)] {
+            card_image.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+        }
+        DynamicImage::ImageRgba8(card_image)
+    }
+
+    #[test]
+    fn test_print_pipeline() {
+        let pipeline = ImagePipeline::for_print(None, 2.0, false);
+        assert!(pipeline.includes_bleed());
+
+        let processed = pipeline.apply(card_with_transparent_corner(), [255, 0, 0]);
+        assert_eq!(processed.dimensions(), (630 + 40, 880 + 40));
+        assert_eq!(processed.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(processed.get_pixel(21, 20).0, [255, 0, 0, 255]);
+        assert_eq!(processed.get_pixel(22, 20).0, [255, 255, 255, 255]);
+        assert_eq!(processed.get_pixel(669, 919).0, [255, 255, 255, 255]);
+
+        // Resampling happens before the bleed is added, which is then measured at the new resolution.
+        let resampled = ImagePipeline::for_print(Some(MM_PER_INCH * 5.0), 2.0, false).apply(card_with_transparent_corner(), [255, 0, 0]);
+        assert_eq!(resampled.dimensions(), (315 + 20, 440 + 20));
+    }
+
+    #[test]
+    fn test_grayscale() {
+        let pipeline = ImagePipeline { transforms: vec![ImageTransform::FillCorners, ImageTransform::Grayscale] };
+        let processed = pipeline.apply(card_with_transparent_corner(), [255, 0, 0]);
+
+        assert!(!pipeline.includes_bleed());
+        assert_eq!(processed.dimensions(), (630, 880));
+        let [red, green, blue, alpha] = processed.get_pixel(0, 0).0;
+        assert!(red == green && green == blue && red < 255 && alpha == 255);
+    }
+}
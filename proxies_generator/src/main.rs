@@ -1,9 +1,69 @@
-use std::{error::Error, io::{Read, Write}};
-use clap::{Parser, ValueEnum};
-use clio::{Input, OutputPath};
-use log::LevelFilter;
+mod config;
+mod download;
+mod embed_images;
+mod image_download;
+mod image_processing;
+mod page_layout;
+mod pdf_output;
+mod print_shop;
+mod tts_sheets;
+mod zip_archive;
 
-use scryfall::{api_interface::{reqwest_wrapper::ReqwestWrapper, ApiInterface}, card_images_helper::{extract_images, ImageUriType}, deck_diff::deck_diff, deck_parsers::{parse_json_data, parse_txt_data}, fetch_card_data::{fetch_deck::FetchDeck, ResolvedCard}};
+use std::{collections::HashMap, error::Error, fs, io::{Read, Write}, path::{Path, PathBuf}, time::Duration};
+use clap::{Parser, Subcommand, ValueEnum};
+use clio::{ClioPath, Input};
+use image::ImageFormat;
+use log::{info, warn, LevelFilter};
+
+use scryfall::{api_interface::{bulk_data_client::BulkDataClient, caching_client::{CacheSettings, CachingClient}, reqwest_wrapper::ReqwestWrapper, ApiInterface, ApiInterfaceBuilder, RequestClient}, card_images_helper::{border_rgb, extract_images, ImageUriType, DEFAULT_CARD_BACK_URL}, deck_diff::deck_diff, deck_list::{DeckList, MergeDuplicates}, deck_parsers::{detect_format, parse_deck_data, DeckFormat}, deck_writers::{write_arena_txt, write_cod, write_csv, write_dek, write_forge_dck, write_scryfall_json, write_set_prefixed_txt, write_xmage_dck}, fetch_card_data::{fetch_deck::{FetchDeck, ResolvedDeck}, ResolvedCard}, tabletop_simulator::{tts_cards, tts_contact_sheets, write_tts_deck, TtsImages}, text_proxies::{text_proxy_sides, write_svg, TextCard}};
+
+use crate::{config::{default_cache_dir, load_config, Config}, download::download_images, embed_images::{inline_images, write_image_assets, EmbedImages, InlineImages}, image_download::ImageLoader, image_processing::ImagePipeline, page_layout::{CutGuides, DuplexFlip, NamedProxyCard, PageLayout, PageSize, ProxyCard, ProxyImage, CARD_HEIGHT_MM, CARD_WIDTH_MM, CROP_MARK_LENGTH_MM}, pdf_output::generate_proxies_pdf, print_shop::{print_shop_pipeline, write_print_shop_order}, tts_sheets::{file_url, stitch_contact_sheet}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Html,
+    Pdf,
+    /// A folder of images with bleed and an MPC Autofill order file, for printing at MakePlayingCards.
+    Mpc,
+    /// Download the card images into a folder, or a ZIP archive when the output ends in .zip.
+    Download,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Magic Online.
+    Dek,
+    /// Cockatrice.
+    Cod,
+    /// Forge, used for files ending in .dck.
+    Forge,
+    /// XMage.
+    Xmage,
+    /// MTG Arena text, used for files ending in .txt.
+    Arena,
+    /// Text with the printing before the name, as in 1 [SET#123] Card Name.
+    SetPrefixed,
+    /// Scryfall deck JSON.
+    Json,
+    /// CSV with the Scryfall ID of each card.
+    Csv,
+    /// Tabletop Simulator saved object.
+    Tts,
+}
+
+impl ExportFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "dek" => Some(Self::Dek),
+            "cod" => Some(Self::Cod),
+            "dck" => Some(Self::Forge),
+            "txt" => Some(Self::Arena),
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum ImageType {
@@ -28,13 +88,30 @@ impl From<ImageType> for ImageUriType {
     }
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the cache of API responses.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Delete every cached API response.
+    Clear,
+}
+
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    #[clap(value_parser)]
-    deck: Input,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[clap(value_parser, required = true)]
+    deck: Option<Input>,
     #[clap(value_parser, default_value="proxies.html")]
-    output: OutputPath,
+    output: ClioPath,
     #[arg(short, long)]
     exclude_basic_lands: bool,
     #[arg(long, short, value_enum)]
@@ -46,13 +123,168 @@ struct Args {
     extra_cards: Vec<String>,
     #[clap(short, long, value_parser)]
     old_deck: Option<Input>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Html)]
+    format: OutputFormat,
+    #[arg(long, value_enum, default_value_t = PageSize::A4)]
+    page_size: PageSize,
+    /// Minimum distance in millimetres between the cards and the edge of PDF pages.
+    #[arg(long, default_value_t = 5.0)]
+    margin: f32,
+    /// Distance in millimetres between adjacent cards on PDF pages.
+    #[arg(long, default_value_t = 0.0)]
+    gutter: f32,
+    /// Distance in millimetres to extend each card's border outwards, to allow for inaccurate cuts.
+    #[arg(long, default_value_t = 0.0)]
+    bleed: f32,
+    /// Draw short marks at the corners of each card, in the space around the cards.
+    #[arg(long)]
+    crop_marks: bool,
+    /// Draw lines along the edges of each card.
+    #[arg(long)]
+    cut_lines: bool,
+    /// Print the back faces of cards on the back of each page, mirrored for the edge the printer flips along.
+    #[arg(long, value_enum)]
+    duplex: Option<DuplexFlip>,
+    /// Image to print on the back of single-faced cards when printing in duplex, also used as the card back in Tabletop Simulator exports.
+    #[arg(long)]
+    card_back: Option<String>,
+    /// Make the HTML output work offline, by inlining each image once or writing them into a folder next to it.
+    #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "inline")]
+    embed_images: Option<EmbedImages>,
+    /// Download the images and process them locally: fill the rounded corners, extend the borders into the bleed and resample them to --dpi. Print shop orders are always processed.
+    #[arg(long)]
+    process_images: bool,
+    /// Resolution to resample processed images to in dots per inch, defaults to 300 for print shop orders and the downloaded resolution otherwise.
+    #[arg(long)]
+    dpi: Option<f32>,
+    /// Convert processed images to greyscale, to save colour ink.
+    #[arg(long)]
+    grayscale: bool,
+    /// Print the name, cost, type line, rules text and stats of each card in a plain frame instead of its image, for cheap playtest prints.
+    #[arg(long)]
+    text_only: bool,
+    /// Download each image once with a manifest of how many copies of each card there are, instead of a file for each copy.
+    #[arg(long)]
+    manifest: bool,
+    /// Also write the resolved deck to this file, in the format given by its extension unless --export-format is set.
+    #[arg(long)]
+    export: Option<PathBuf>,
+    #[arg(long, value_enum)]
+    export_format: Option<ExportFormat>,
+    /// Stitch the card images of Tabletop Simulator exports into contact sheets next to the exported file, instead of loading each image from Scryfall.
+    #[arg(long)]
+    tts_sheets: bool,
+    /// Resolve cards from a downloaded Scryfall bulk data file instead of the API.
+    #[arg(long)]
+    bulk_data: Option<PathBuf>,
+    /// Directory to store API responses in, defaults to the user's cache directory.
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+    /// Always request card data from the API instead of using cached responses.
+    #[arg(long)]
+    no_cache: bool,
+    /// Number of hours cached API responses stay valid for.
+    #[arg(long, default_value_t = 24)]
+    cache_ttl: u64,
+    /// Maximum size of the API response cache in megabytes, the oldest responses are removed first.
+    #[arg(long, default_value_t = 100)]
+    cache_max_size: u64,
+    /// TOML file with default values for the API and cache settings, keys are named after the flags.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Base URL of the Scryfall API, such as a local mirror.
+    #[arg(long)]
+    api_endpoint: Option<String>,
+    /// User agent sent with every API request.
+    #[arg(long)]
+    user_agent: Option<String>,
+    /// Number of seconds to wait for each API request to complete.
+    #[arg(long)]
+    timeout: Option<u64>,
+    /// Number of seconds to wait for a connection to the API.
+    #[arg(long)]
+    connect_timeout: Option<u64>,
+    /// Proxy URL to send API requests through.
+    #[arg(long)]
+    proxy: Option<String>,
+    /// Maximum number of API requests to send each second.
+    #[arg(long)]
+    requests_per_second: Option<u32>,
+}
+
+fn apply_config(args: &mut Args, config: Config) {
+    args.api_endpoint = args.api_endpoint.take().or(config.api_endpoint);
+    args.user_agent = args.user_agent.take().or(config.user_agent);
+    args.timeout = args.timeout.or(config.timeout);
+    args.connect_timeout = args.connect_timeout.or(config.connect_timeout);
+    args.proxy = args.proxy.take().or(config.proxy);
+    args.requests_per_second = args.requests_per_second.or(config.requests_per_second);
+    args.cache_dir = args.cache_dir.take().or(config.cache_dir);
+}
+
+fn api_interface_builder<Client: RequestClient>(args: &Args) -> ApiInterfaceBuilder<Client> {
+    let mut builder = ApiInterface::builder();
+
+    if let Some(api_endpoint) = &args.api_endpoint {
+        builder = builder.endpoint(api_endpoint);
+    }
+    if let Some(user_agent) = &args.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(timeout) = args.timeout {
+        builder = builder.timeout(Duration::from_secs(timeout));
+    }
+    if let Some(connect_timeout) = args.connect_timeout {
+        builder = builder.connect_timeout(Duration::from_secs(connect_timeout));
+    }
+    if let Some(proxy) = &args.proxy {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(requests_per_second) = args.requests_per_second {
+        builder = builder.requests_per_second(requests_per_second);
+    }
+
+    builder
 }
 
-fn generate_proxies_html(card_images: &Vec<String>, extra_cards: &[String]) -> Result<String, Box<dyn Error>> {
-    let mut html = "<!DOCTYPE html><html><style>@page {size: auto;margin: 5mm 10mm;}.card{margin: 0;page-break-inside: avoid;width: 63mm;height: 88mm;}</style><body style=\"margin: 0 0 30px;padding: 0;font-size: 0;\">".to_owned();
+// Inline images are only written once, as a CSS class that every copy of the card uses, so the page needs no script.
+// Processed images already include their bleed, so they fill the padding instead of a background colour.
+fn generate_proxies_html(card_images: &[ProxyImage], bleed_mm: f32, guides: CutGuides, inline_images: Option<&InlineImages>, images_include_bleed: bool) -> Result<String, Box<dyn Error>> {
+    let card_style = if images_include_bleed {
+        format!("width: {}mm;height: {}mm;padding: 0;", CARD_WIDTH_MM + 2.0 * bleed_mm, CARD_HEIGHT_MM + 2.0 * bleed_mm)
+    } else {
+        format!("width: {CARD_WIDTH_MM}mm;height: {CARD_HEIGHT_MM}mm;padding: {bleed_mm}mm;")
+    };
+
+    let inline_image_rules: String = inline_images.map(|inline_images| inline_images.data_uris.iter().enumerate()
+        .map(|(image_index, data_uri)| format!(".image-{image_index}{{content: url(\"{data_uri}\");}}"))
+        .collect()).unwrap_or_default();
 
-    for image_url in extra_cards.iter().chain(card_images) {
-        html += &format!("<img src=\"{image_url}\" class=\"card\"/>");
+    let mut html = format!("<!DOCTYPE html><html><style>\
+@page {{size: auto;margin: 5mm 10mm;}}\
+.card-wrapper{{display: inline-block;position: relative;page-break-inside: avoid;}}\
+.card{{margin: 0;{card_style}vertical-align: top;}}\
+.crop-marks .card-wrapper::before,.crop-marks .card-wrapper::after{{content: \"\";position: absolute;z-index: -1;border: 0 solid #000;}}\
+.crop-marks .card-wrapper::before{{top: {bleed_mm}mm;bottom: {bleed_mm}mm;left: -{CROP_MARK_LENGTH_MM}mm;right: -{CROP_MARK_LENGTH_MM}mm;border-width: 0.2mm 0;}}\
+.crop-marks .card-wrapper::after{{left: {bleed_mm}mm;right: {bleed_mm}mm;top: -{CROP_MARK_LENGTH_MM}mm;bottom: -{CROP_MARK_LENGTH_MM}mm;border-width: 0 0.2mm;}}\
+.cut-lines .card{{outline: 0.1mm dashed #808080;outline-offset: -{bleed_mm}mm;}}\
+{inline_image_rules}</style><body class=\"{}{}\" style=\"margin: 0 0 30px;padding: 0;font-size: 0;isolation: isolate;\">",
+        if guides.crop_marks { "crop-marks " } else { "" },
+        if guides.cut_lines { "cut-lines" } else { "" },
+    );
+
+    for card_image in card_images {
+        let image_attributes = match inline_images.and_then(|inline_images| inline_images.indices.get(&card_image.source)) {
+            Some(image_index) => format!("class=\"card image-{image_index}\""),
+            None => format!("src=\"{}\" class=\"card\"", card_image.source),
+        };
+
+        if bleed_mm > 0.0 && !images_include_bleed {
+            let [red, green, blue] = card_image.border_rgb;
+            html += &format!("<span class=\"card-wrapper\"><img {image_attributes} style=\"background-color: rgb({red}, {green}, {blue});\"/></span>");
+        } else {
+            html += &format!("<span class=\"card-wrapper\"><img {image_attributes}/></span>");
+        }
     }
 
     html += "</body></html>";
@@ -60,26 +292,144 @@ fn generate_proxies_html(card_images: &Vec<String>, extra_cards: &[String]) -> R
     Ok(html)
 }
 
-async fn get_cards_from_file(deck_file: &mut Input, interface: &mut ApiInterface<ReqwestWrapper>, include_tokens: bool) -> Vec<ResolvedCard> {
-    let deck_file_extension = match deck_file.path().extension() {
-        Some(extension) => extension.to_string_lossy().into_owned(),
-        None => panic!("Could not find extension of file {}", deck_file.path()),
-    };
+// Processed images are encoded as JPEG, which is far smaller than PNG for card art and has no need for transparency once the corners are filled.
+async fn load_card_images<'a>(proxy_images: impl IntoIterator<Item = &'a ProxyImage>, pipeline: Option<&ImagePipeline>, image_loader: &ImageLoader) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
+    match pipeline {
+        Some(pipeline) => pipeline.load_processed_images(proxy_images, ImageFormat::Jpeg, image_loader).await,
+        None => image_loader.load_images(proxy_images.into_iter().map(|proxy_image| &proxy_image.source)).await,
+    }
+}
 
+// The extension is trusted when it is a known one, other files such as standard input are recognised by their contents.
+fn read_deck_list(deck_file: &mut Input) -> DeckList {
     let mut deck_data = String::new();
     deck_file.read_to_string(&mut deck_data).expect("Could not open deck file");
 
-    let unresolved_cards = match deck_file_extension.as_str() {
-        "txt" | "dec" => {
-            parse_txt_data(&deck_data).expect("Could not parse deck file")
-        },
-        "json" => {
-            parse_json_data(&deck_data).expect("Could not parse deck file")
-        },
-        _ => panic!("File extension {deck_file_extension} is not supported"),
+    let deck_format = deck_file.path().extension()
+        .and_then(|extension| DeckFormat::from_extension(&extension.to_string_lossy()))
+        .unwrap_or_else(|| detect_format(&deck_data));
+    info!("Reading {} as a {deck_format} deck", deck_file.path());
+
+    let unresolved_cards = parse_deck_data(&deck_data, deck_format).expect("Could not parse deck file");
+
+    for diagnostic in &unresolved_cards.diagnostics {
+        warn!("{}: {diagnostic}", deck_file.path());
+    }
+
+    // Sections are kept apart, so exported decks keep their sideboard.
+    unresolved_cards.merge_duplicates(MergeDuplicates::WithinSections)
+}
+
+async fn get_cards_from_file<Client: RequestClient>(deck_file: &mut Input, interface: &ApiInterface<Client>, include_tokens: bool) -> Vec<ResolvedCard> {
+    interface.fetch_deck(&read_deck_list(deck_file), include_tokens).await.expect("Could not resolve deck cards")
+}
+
+struct ExportSettings<'a> {
+    path: &'a Path,
+    format: Option<ExportFormat>,
+    image_type: ImageUriType,
+    card_back: Option<&'a str>,
+    tts_sheets: bool,
+    image_loader: &'a ImageLoader,
+}
+
+// Contact sheets are written next to the saved object, named after it.
+async fn export_tts(deck_name: &str, main_deck: &[ResolvedCard], sideboard: &[ResolvedCard], settings: &ExportSettings<'_>) -> String {
+    let main_deck = tts_cards(main_deck, settings.image_type);
+    let sideboard = tts_cards(sideboard, settings.image_type);
+
+    let back_url = match settings.card_back {
+        Some(card_back) if card_back.starts_with("http://") || card_back.starts_with("https://") => card_back.to_owned(),
+        Some(card_back) => file_url(Path::new(card_back)).expect("Could not find card back image"),
+        None => DEFAULT_CARD_BACK_URL.to_owned(),
+    };
+
+    if !settings.tts_sheets {
+        return write_tts_deck(deck_name, &main_deck, &sideboard, TtsImages::Individual, &back_url);
+    }
+
+    let sheets = tts_contact_sheets(&[&main_deck, &sideboard]);
+    let loaded_images = settings.image_loader.load_images(sheets.iter().flatten()).await.expect("Could not load card images");
+
+    let mut sheet_urls = Vec::new();
+    for (index, sheet) in sheets.iter().enumerate() {
+        let sheet_path = settings.path.with_file_name(format!("{deck_name}-sheet-{}.jpg", index + 1));
+        let images: Vec<&[u8]> = sheet.iter().map(|source| loaded_images[source].as_slice()).collect();
+
+        info!("Writing contact sheet {}", sheet_path.display());
+        fs::write(&sheet_path, stitch_contact_sheet(&images).expect("Could not stitch contact sheet")).expect("Could not write contact sheet");
+        sheet_urls.push(file_url(&sheet_path).expect("Could not find contact sheet"));
+    }
+
+    write_tts_deck(deck_name, &main_deck, &sideboard, TtsImages::ContactSheets(&sheet_urls), &back_url)
+}
+
+async fn export_deck(resolved_deck: &ResolvedDeck, settings: &ExportSettings<'_>) {
+    let export_path = settings.path;
+    let Some(export_format) = settings.format.or_else(|| ExportFormat::from_path(export_path)) else {
+        panic!("Could not tell the format to export {} in from its extension, use --export-format", export_path.display());
+    };
+
+    let ResolvedDeck { main_deck, sideboard, .. } = resolved_deck;
+
+    let deck_name = || export_path.file_stem().map(|file_stem| file_stem.to_string_lossy()).unwrap_or_default();
+    let export_data = match export_format {
+        ExportFormat::Dek => write_dek(main_deck, sideboard),
+        ExportFormat::Cod => write_cod(main_deck, sideboard),
+        ExportFormat::Forge => write_forge_dck(&deck_name(), main_deck, sideboard),
+        ExportFormat::Xmage => write_xmage_dck(main_deck, sideboard),
+        ExportFormat::Arena => write_arena_txt(main_deck, sideboard),
+        ExportFormat::SetPrefixed => write_set_prefixed_txt(main_deck, sideboard),
+        ExportFormat::Json => write_scryfall_json(&deck_name(), main_deck, sideboard),
+        ExportFormat::Csv => write_csv(main_deck, sideboard),
+        ExportFormat::Tts => export_tts(&deck_name(), main_deck, sideboard, settings).await,
     };
 
-    interface.fetch_deck(&unresolved_cards, include_tokens).await.expect("Could not resolve deck cards")
+    fs::write(export_path, export_data).expect("Could not write exported deck file");
+}
+
+async fn get_decks<Client: RequestClient>(interface: &ApiInterface<Client>, deck_file: &mut Input, old_deck_file: Option<&mut Input>, include_tokens: bool, export_settings: Option<&ExportSettings<'_>>) -> (Vec<ResolvedCard>, Option<Vec<ResolvedCard>>) {
+    // The main deck and sideboard are resolved apart for the export, and added up again for the proxies.
+    let resolved_deck = interface.fetch_deck_sections(&read_deck_list(deck_file), include_tokens).await.expect("Could not resolve deck cards");
+
+    if let Some(export_settings) = export_settings {
+        export_deck(&resolved_deck, export_settings).await;
+    }
+
+    let cards = resolved_deck.cards();
+
+    let old_cards = match old_deck_file {
+        Some(old_deck_file) => Some(get_cards_from_file(old_deck_file, interface, include_tokens).await),
+        None => None,
+    };
+
+    (cards, old_cards)
+}
+
+// Cards are named after their printing, custom cards after their position on the command line.
+fn named_proxy_cards(extra_cards: &[String], resolved_cards: &[ResolvedCard], exclude_basic_lands: bool, image_type: ImageUriType) -> Vec<NamedProxyCard> {
+    extra_cards.iter().enumerate()
+        .map(|(index, extra_card)| NamedProxyCard {
+            name: "Custom card".to_owned(),
+            file_stem: format!("custom-{}", index + 1),
+            front: ProxyImage { source: extra_card.clone(), border_rgb: [0, 0, 0] },
+            back: None,
+            count: 1,
+        })
+        .chain(resolved_cards.iter().filter_map(|resolved_card| {
+            let (card, card_images) = extract_images(vec![resolved_card.card.clone()], exclude_basic_lands, image_type).pop()?;
+            let card_border_rgb = border_rgb(&card);
+            let mut faces = card_images.into_iter().map(|source| ProxyImage { source, border_rgb: card_border_rgb });
+
+            Some(NamedProxyCard {
+                name: card.name.clone(),
+                file_stem: format!("{}-{}", card.set, card.collector_number),
+                front: faces.next()?,
+                back: faces.next(),
+                count: resolved_card.count,
+            })
+        }))
+        .collect()
 }
 
 #[tokio::main]
@@ -95,27 +445,159 @@ async fn main() {
 
     logging_builder.init();
 
-    let mut interface = ApiInterface::<ReqwestWrapper>::new().expect("Could not initialise HTTP client");
+    let config = load_config(args.config.as_deref()).expect("Could not read config file");
+    apply_config(&mut args, config);
+
+    let cache_dir = args.cache_dir.clone().unwrap_or_else(default_cache_dir);
+
+    if let Some(Command::Cache { action: CacheAction::Clear }) = args.command {
+        CachingClient::<ReqwestWrapper>::clear(&cache_dir).expect("Could not clear the response cache");
+        println!("Cleared response cache in {}", cache_dir.display());
+        return;
+    }
+
+    let mut deck = args.deck.take().expect("A deck file is required without a subcommand");
+
+    if args.duplex.is_some() && args.format != OutputFormat::Pdf {
+        panic!("Duplex printing requires exact page layouts, use the PDF format instead");
+    }
 
-    let cards = get_cards_from_file(&mut args.deck, &mut interface, args.include_tokens).await;
+    // Print shops need the largest images, which only the PNG images are.
+    let default_image_type = if args.format == OutputFormat::Mpc { ImageType::Png } else { ImageType::Large };
+    let image_type: ImageUriType = args.image_type.unwrap_or(default_image_type).into();
 
-    let card_images = if let Some(mut old_deck) = args.old_deck {
-        let old_cards = get_cards_from_file(&mut old_deck, &mut interface, args.include_tokens).await;
+    let image_loader = ImageLoader::new(api_interface_builder::<ReqwestWrapper>(&args).settings()).expect("Could not initialise HTTP client");
+
+    let export_settings = args.export.as_deref().map(|path| ExportSettings {
+        path,
+        format: args.export_format,
+        image_type,
+        card_back: args.card_back.as_deref(),
+        tts_sheets: args.tts_sheets,
+        image_loader: &image_loader,
+    });
+
+    let (cards, old_cards) = if let Some(bulk_data) = &args.bulk_data {
+        let interface = api_interface_builder(&args).build_with_client(BulkDataClient::from_file(bulk_data).expect("Could not load bulk data file"));
+        get_decks(&interface, &mut deck, args.old_deck.as_mut(), args.include_tokens, export_settings.as_ref()).await
+    } else if args.no_cache {
+        let interface = api_interface_builder::<ReqwestWrapper>(&args).build().expect("Could not initialise HTTP client");
+        get_decks(&interface, &mut deck, args.old_deck.as_mut(), args.include_tokens, export_settings.as_ref()).await
+    } else {
+        let cache_settings = CacheSettings {
+            directory: cache_dir,
+            time_to_live: Duration::from_secs(args.cache_ttl * 60 * 60),
+            max_size_bytes: args.cache_max_size * 1024 * 1024,
+        };
+        let builder = api_interface_builder(&args);
+        let http_client = ReqwestWrapper::build(builder.settings()).expect("Could not initialise HTTP client");
+        let interface = builder.build_with_client(CachingClient::new(http_client, cache_settings).expect("Could not initialise response cache"));
+        get_decks(&interface, &mut deck, args.old_deck.as_mut(), args.include_tokens, export_settings.as_ref()).await
+    };
+
+    let resolved_cards: Vec<ResolvedCard> = if let Some(old_cards) = old_cards {
         let difference = deck_diff(old_cards, cards);
 
         println!("Added:{}\n", difference.added.iter().fold("".to_owned(), |acc, card| format!("{}\n{}", acc, card.name)));
         println!("Removed:{}\n", difference.removed.iter().fold("".to_owned(), |acc, card| format!("{}\n{}", acc, card.name)));
 
-        extract_images(difference.added, args.exclude_basic_lands, args.image_type.unwrap_or(ImageType::Large).into())
+        // Each added copy is listed separately.
+        difference.added.into_iter().map(|card| ResolvedCard { count: 1, card }).collect()
     } else {
         for card in &cards {
             println!("{card}");
         }
 
-        extract_images(cards.into_iter().map(|card| card.card).collect(), args.exclude_basic_lands, args.image_type.unwrap_or(ImageType::Large).into())
+        cards
     };
 
-    let proxies_html = generate_proxies_html(&card_images.into_iter().flat_map(|(_, card_images)| card_images).collect(), &args.extra_cards).expect("Could not generate proxies HTML content");
+    let card_images = extract_images(resolved_cards.iter().map(|resolved_card| resolved_card.card.clone()).collect(), args.exclude_basic_lands, image_type);
+
+    // Only double-faced cards have a back face image, other cards use the generic card back if one is given.
+    let generic_back = args.card_back.map(|source| ProxyImage { source, border_rgb: [0, 0, 0] });
+
+    // Text proxies are given sources of their own, which the outputs render from these instead of loading an image.
+    let mut text_cards: HashMap<String, TextCard> = HashMap::new();
 
-    args.output.create().expect("Could not create proxies HTML file").write_all(proxies_html.as_bytes()).expect("Could not write proxies HTML file");
+    // Custom cards have no known border colour, black is the most common.
+    let proxy_cards: Vec<ProxyCard> = args.extra_cards.iter()
+        .map(|extra_card| ProxyCard {
+            front: ProxyImage { source: extra_card.clone(), border_rgb: [0, 0, 0] },
+            back: None,
+        })
+        .chain(card_images.into_iter().filter_map(|(card, card_images)| {
+            let (card_images, card_border_rgb) = if args.text_only {
+                let sources = text_proxy_sides(&card).into_iter().enumerate().map(|(side, text_card)| {
+                    let source = format!("text:{}:{side}", card.id);
+                    text_cards.insert(source.clone(), text_card);
+                    source
+                }).collect();
+                (sources, [255, 255, 255])
+            } else {
+                (card_images, border_rgb(&card))
+            };
+            let mut faces = card_images.into_iter().map(|source| ProxyImage { source, border_rgb: card_border_rgb });
+
+            Some(ProxyCard { front: faces.next()?, back: faces.next() })
+        }))
+        .map(|card| match (&card.back, &generic_back, args.duplex) {
+            (None, Some(generic_back), Some(_)) => ProxyCard { back: Some(generic_back.clone()), ..card },
+            _ => card,
+        })
+        .collect();
+    let guides = CutGuides { crop_marks: args.crop_marks, cut_lines: args.cut_lines };
+    let pipeline = (args.process_images || args.dpi.is_some() || args.grayscale)
+        .then(|| ImagePipeline::for_print(args.dpi, args.bleed, args.grayscale));
+    let images_include_bleed = pipeline.as_ref().is_some_and(ImagePipeline::includes_bleed);
+
+    match args.format {
+        OutputFormat::Html => {
+            let proxy_images: Vec<ProxyImage> = proxy_cards.into_iter().flat_map(|card| [Some(card.front), card.back]).flatten().collect();
+            // Processed images and text proxies only exist locally, so they are always embedded.
+            let embed_images = args.embed_images.or((pipeline.is_some() || !text_cards.is_empty()).then_some(EmbedImages::Inline));
+            let (proxy_images, inline_images) = match embed_images {
+                Some(EmbedImages::Assets) if args.output.is_std() => panic!("Image assets are written next to the HTML file, which needs a file path"),
+                Some(embed_images) => {
+                    let image_proxies = proxy_images.iter().filter(|proxy_image| !text_cards.contains_key(&proxy_image.source));
+                    let mut loaded_images = load_card_images(image_proxies, pipeline.as_ref(), &image_loader).await.expect("Could not load card images");
+                    loaded_images.extend(text_cards.iter().map(|(source, text_card)| (source.clone(), write_svg(text_card).into_bytes())));
+
+                    if embed_images == EmbedImages::Inline {
+                        let inline_images = inline_images(&proxy_images, &loaded_images).expect("Could not inline card images");
+                        (proxy_images, Some(inline_images))
+                    } else {
+                        (write_image_assets(&proxy_images, &loaded_images, args.output.path()).expect("Could not write image assets"), None)
+                    }
+                },
+                None => (proxy_images, None),
+            };
+            let proxies_html = generate_proxies_html(&proxy_images, args.bleed, guides, inline_images.as_ref(), images_include_bleed).expect("Could not generate proxies HTML content");
+
+            args.output.create().expect("Could not create proxies HTML file").write_all(proxies_html.as_bytes()).expect("Could not write proxies HTML file");
+        },
+        OutputFormat::Pdf => {
+            let layout = PageLayout::new(args.page_size, args.margin, args.gutter, args.bleed).expect("Could not lay out cards on the page");
+            let pages = layout.paginate(proxy_cards, args.duplex);
+            let image_proxies = pages.iter().flatten().flatten().filter(|proxy_image| !text_cards.contains_key(&proxy_image.source));
+            let loaded_images = load_card_images(image_proxies, pipeline.as_ref(), &image_loader).await.expect("Could not load card images");
+
+            let proxies_pdf = generate_proxies_pdf(&pages, &loaded_images, &layout, guides, images_include_bleed, &text_cards).expect("Could not generate proxies PDF content");
+
+            args.output.create().expect("Could not create proxies PDF file").write_all(&proxies_pdf).expect("Could not write proxies PDF file");
+        },
+        OutputFormat::Mpc | OutputFormat::Download if args.text_only => panic!("Text proxies are only rendered in the HTML and PDF outputs"),
+        OutputFormat::Mpc => {
+            let print_shop_cards = named_proxy_cards(&args.extra_cards, &resolved_cards, args.exclude_basic_lands, image_type);
+            let common_back = generic_back.unwrap_or_else(|| ProxyImage { source: DEFAULT_CARD_BACK_URL.to_owned(), border_rgb: [0, 0, 0] });
+
+            let print_shop_pipeline = print_shop_pipeline(args.dpi, args.grayscale);
+
+            write_print_shop_order(args.output.path(), &print_shop_cards, &common_back, &print_shop_pipeline, &image_loader).await.expect("Could not write print shop order");
+        },
+        OutputFormat::Download => {
+            let named_cards = named_proxy_cards(&args.extra_cards, &resolved_cards, args.exclude_basic_lands, image_type);
+
+            download_images(args.output.path(), &named_cards, !args.manifest, &image_loader).await.expect("Could not download card images");
+        },
+    }
 }
@@ -0,0 +1,235 @@
+use std::{error::Error, fmt::Display};
+use clap::ValueEnum;
+
+pub const CARD_WIDTH_MM: f32 = 63.0;
+pub const CARD_HEIGHT_MM: f32 = 88.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    pub fn dimensions_mm(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoCardsFitError {
+    page_size: PageSize,
+    margin_mm: f32,
+}
+
+impl Display for NoCardsFitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No cards fit on a {:?} page with a margin of {}mm", self.page_size, self.margin_mm)
+    }
+}
+
+impl Error for NoCardsFitError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuplexFlip {
+    LongEdge,
+    ShortEdge,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyImage {
+    pub source: String,
+    pub border_rgb: [u8; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyCard {
+    pub front: ProxyImage,
+    pub back: Option<ProxyImage>,
+}
+
+// Outputs that write each card to its own files name them after the card, copies share the same files.
+#[derive(Debug, Clone)]
+pub struct NamedProxyCard {
+    pub name: String,
+    // Files are named after this, followed by the face.
+    pub file_stem: String,
+    pub front: ProxyImage,
+    pub back: Option<ProxyImage>,
+    pub count: usize,
+}
+
+// Each page holds one slot per position in the grid, empty slots are left blank.
+pub type PageSlots = Vec<Option<ProxyImage>>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CutGuides {
+    pub crop_marks: bool,
+    pub cut_lines: bool,
+}
+
+// Crop marks run outwards from the edge of the bleed.
+pub const CROP_MARK_LENGTH_MM: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardPosition {
+    pub x_mm: f32,
+    pub y_mm: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PageLayout {
+    pub page_size: PageSize,
+    pub gutter_mm: f32,
+    pub bleed_mm: f32,
+    columns: usize,
+    rows: usize,
+}
+
+impl PageLayout {
+    pub fn new(page_size: PageSize, margin_mm: f32, gutter_mm: f32, bleed_mm: f32) -> Result<Self, NoCardsFitError> {
+        let (page_width, page_height) = page_size.dimensions_mm();
+
+        // Adding one gutter to the printable area accounts for there being one less gutter than cards.
+        let columns = ((page_width - 2.0 * margin_mm + gutter_mm) / (CARD_WIDTH_MM + 2.0 * bleed_mm + gutter_mm)).floor();
+        let rows = ((page_height - 2.0 * margin_mm + gutter_mm) / (CARD_HEIGHT_MM + 2.0 * bleed_mm + gutter_mm)).floor();
+
+        if columns < 1.0 || rows < 1.0 {
+            return Err(NoCardsFitError { page_size, margin_mm });
+        }
+
+        Ok(Self {
+            page_size,
+            gutter_mm,
+            bleed_mm,
+            columns: columns as usize,
+            rows: rows as usize,
+        })
+    }
+
+    pub fn cards_per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell_width_mm(&self) -> f32 {
+        CARD_WIDTH_MM + 2.0 * self.bleed_mm
+    }
+
+    fn cell_height_mm(&self) -> f32 {
+        CARD_HEIGHT_MM + 2.0 * self.bleed_mm
+    }
+
+    pub fn grid_width_mm(&self) -> f32 {
+        self.columns as f32 * (self.cell_width_mm() + self.gutter_mm) - self.gutter_mm
+    }
+
+    pub fn grid_height_mm(&self) -> f32 {
+        self.rows as f32 * (self.cell_height_mm() + self.gutter_mm) - self.gutter_mm
+    }
+
+    // Positions are the top left corner of the card without its bleed, measured from the top left corner of the page.
+    // The grid is centred inside the margins.
+    pub fn card_position(&self, index_on_page: usize) -> CardPosition {
+        let (page_width, page_height) = self.page_size.dimensions_mm();
+        let column = index_on_page % self.columns;
+        let row = index_on_page / self.columns;
+
+        CardPosition {
+            x_mm: (page_width - self.grid_width_mm()) / 2.0 + column as f32 * (self.cell_width_mm() + self.gutter_mm) + self.bleed_mm,
+            y_mm: (page_height - self.grid_height_mm()) / 2.0 + row as f32 * (self.cell_height_mm() + self.gutter_mm) + self.bleed_mm,
+        }
+    }
+
+    // Returns the position on the back of the page that lies behind the given position on the front.
+    // Printers keep the back upright for the selected flip, so only the grid is mirrored, never the images.
+    pub fn mirrored_index(&self, index_on_page: usize, flip: DuplexFlip) -> usize {
+        let column = index_on_page % self.columns;
+        let row = index_on_page / self.columns;
+
+        match flip {
+            DuplexFlip::LongEdge => row * self.columns + (self.columns - 1 - column),
+            DuplexFlip::ShortEdge => (self.rows - 1 - row) * self.columns + column,
+        }
+    }
+
+    // Without duplex printing both faces of double-faced cards are placed next to each other on the same side.
+    // With duplex printing every page of fronts is followed by a page of the matching backs.
+    pub fn paginate(&self, cards: Vec<ProxyCard>, duplex: Option<DuplexFlip>) -> Vec<PageSlots> {
+        let Some(flip) = duplex else {
+            let images: Vec<ProxyImage> = cards.into_iter().flat_map(|card| [Some(card.front), card.back]).flatten().collect();
+            return images.chunks(self.cards_per_page()).map(|page_images| page_images.iter().cloned().map(Some).collect()).collect();
+        };
+
+        let mut pages = Vec::new();
+        for page_cards in cards.chunks(self.cards_per_page()) {
+            let mut back_page: PageSlots = vec![None; self.cards_per_page()];
+
+            for (index_on_page, card) in page_cards.iter().enumerate() {
+                back_page[self.mirrored_index(index_on_page, flip)] = card.back.clone();
+            }
+
+            pages.push(page_cards.iter().map(|card| Some(card.front.clone())).collect());
+            pages.push(back_page);
+        }
+
+        pages
+    }
+
+    // Returns the horizontal and vertical positions of every card edge, for drawing cut lines across the whole page.
+    pub fn cut_positions_mm(&self) -> (Vec<f32>, Vec<f32>) {
+        let column_edges = (0..self.columns)
+            .map(|column| self.card_position(column).x_mm)
+            .flat_map(|x| [x, x + CARD_WIDTH_MM])
+            .collect();
+        let row_edges = (0..self.rows)
+            .map(|row| self.card_position(row * self.columns).y_mm)
+            .flat_map(|y| [y, y + CARD_HEIGHT_MM])
+            .collect();
+
+        (column_edges, row_edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_geometry() {
+        let layout = PageLayout::new(PageSize::A4, 5.0, 0.0, 0.0).unwrap();
+        assert_eq!(layout.cards_per_page(), 9);
+        assert_eq!(layout.card_position(0), CardPosition { x_mm: 10.5, y_mm: 16.5 });
+        assert_eq!(layout.card_position(4), CardPosition { x_mm: 73.5, y_mm: 104.5 });
+
+        // A wide gutter leaves room for only two columns and two rows on Letter paper.
+        let layout = PageLayout::new(PageSize::Letter, 5.0, 10.0, 0.0).unwrap();
+        assert_eq!(layout.cards_per_page(), 4);
+        assert_eq!(layout.grid_width_mm(), 136.0);
+        assert_eq!(layout.grid_height_mm(), 186.0);
+
+        assert!(PageLayout::new(PageSize::A4, 80.0, 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_duplex_mirroring() {
+        // A4 with 5mm margins fits a 3x3 grid.
+        let layout = PageLayout::new(PageSize::A4, 5.0, 0.0, 0.0).expect("Cards should fit on an A4 page");
+        assert_eq!(layout.cards_per_page(), 9);
+
+        assert_eq!(layout.mirrored_index(0, DuplexFlip::LongEdge), 2);
+        assert_eq!(layout.mirrored_index(4, DuplexFlip::LongEdge), 4);
+        assert_eq!(layout.mirrored_index(5, DuplexFlip::LongEdge), 3);
+        assert_eq!(layout.mirrored_index(0, DuplexFlip::ShortEdge), 6);
+        assert_eq!(layout.mirrored_index(5, DuplexFlip::ShortEdge), 5);
+        assert_eq!(layout.mirrored_index(7, DuplexFlip::ShortEdge), 1);
+
+        // Mirrored positions must land exactly behind the front, so the grid has to be centred on the page.
+        let (page_width, _) = PageSize::A4.dimensions_mm();
+        let front = layout.card_position(0);
+        let back = layout.card_position(layout.mirrored_index(0, DuplexFlip::LongEdge));
+        assert!((front.x_mm - (page_width - back.x_mm - CARD_WIDTH_MM)).abs() < 0.001);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{collections::HashMap, error::Error, fmt::Display};
+use image::{ColorType, DynamicImage, GenericImageView, ImageFormat};
+use miniz_oxide::deflate::{compress_to_vec_zlib, CompressionLevel};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use scryfall::text_proxies::{text_width_mm, FontWeight, TextAnchor, TextCard, LINE_WIDTH_MM};
+
+use crate::page_layout::{CutGuides, PageLayout, PageSlots, CARD_HEIGHT_MM, CARD_WIDTH_MM, CROP_MARK_LENGTH_MM};
+
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+#[derive(Debug, Clone)]
+pub struct MissingImageError {
+    source: String,
+}
+
+impl Display for MissingImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Image {} was not loaded before generating the PDF", self.source)
+    }
+}
+
+impl Error for MissingImageError {}
+
+struct RefAllocator {
+    next: i32,
+}
+
+impl RefAllocator {
+    fn next(&mut self) -> Ref {
+        let allocated = Ref::new(self.next);
+        self.next += 1;
+        allocated
+    }
+}
+
+fn write_image(pdf: &mut Pdf, refs: &mut RefAllocator, data: &[u8]) -> Result<Ref, Box<dyn Error>> {
+    let image_id = refs.next();
+    let format = image::guess_format(data)?;
+    let dynamic = image::load_from_memory(data)?;
+
+    // JPEG data can be embedded as-is, everything else is re-encoded as raw samples with a separate alpha mask.
+    let (filter, encoded, mask) = if format == ImageFormat::Jpeg && dynamic.color() == ColorType::Rgb8 {
+        (Filter::DctDecode, data.to_vec(), None)
+    } else {
+        encode_raw_samples(&dynamic)
+    };
+
+    let s_mask_id = mask.as_ref().map(|_| refs.next());
+
+    let mut image = pdf.image_xobject(image_id, &encoded);
+    image.filter(filter);
+    image.width(dynamic.width() as i32);
+    image.height(dynamic.height() as i32);
+    image.color_space().device_rgb();
+    image.bits_per_component(8);
+    if let Some(s_mask_id) = s_mask_id {
+        image.s_mask(s_mask_id);
+    }
+    image.finish();
+
+    if let (Some(s_mask_id), Some(mask)) = (s_mask_id, mask) {
+        let mut s_mask = pdf.image_xobject(s_mask_id, &mask);
+        s_mask.filter(Filter::FlateDecode);
+        s_mask.width(dynamic.width() as i32);
+        s_mask.height(dynamic.height() as i32);
+        s_mask.color_space().device_gray();
+        s_mask.bits_per_component(8);
+    }
+
+    Ok(image_id)
+}
+
+fn encode_raw_samples(dynamic: &DynamicImage) -> (Filter, Vec<u8>, Option<Vec<u8>>) {
+    let level = CompressionLevel::DefaultLevel as u8;
+    let encoded = compress_to_vec_zlib(dynamic.to_rgb8().as_raw(), level);
+
+    let mask = dynamic.color().has_alpha().then(|| {
+        let alphas: Vec<u8> = dynamic.pixels().map(|(_, _, pixel)| pixel.0[3]).collect();
+        compress_to_vec_zlib(&alphas, level)
+    });
+
+    (Filter::FlateDecode, encoded, mask)
+}
+
+const CROP_MARK_WIDTH_MM: f32 = 0.2;
+const CUT_LINE_WIDTH_MM: f32 = 0.1;
+
+// Converts a line from top left page coordinates in millimetres to PDF coordinates and adds it to the current path.
+fn line_mm(content: &mut Content, page_height: f32, (x1, y1): (f32, f32), (x2, y2): (f32, f32)) {
+    content.move_to(x1 * POINTS_PER_MM, (page_height - y1) * POINTS_PER_MM);
+    content.line_to(x2 * POINTS_PER_MM, (page_height - y2) * POINTS_PER_MM);
+}
+
+fn draw_crop_marks(content: &mut Content, layout: &PageLayout, page_slots: &PageSlots) {
+    let (_, page_height) = layout.page_size.dimensions_mm();
+    let bleed = layout.bleed_mm;
+
+    content.save_state();
+    content.set_stroke_gray(0.0);
+    content.set_line_width(CROP_MARK_WIDTH_MM * POINTS_PER_MM);
+
+    for (index_on_page, _) in page_slots.iter().enumerate().filter(|(_, slot)| slot.is_some()) {
+        let position = layout.card_position(index_on_page);
+        let left = position.x_mm;
+        let right = position.x_mm + CARD_WIDTH_MM;
+        let top = position.y_mm;
+        let bottom = position.y_mm + CARD_HEIGHT_MM;
+
+        for y in [top, bottom] {
+            line_mm(content, page_height, (left - bleed - CROP_MARK_LENGTH_MM, y), (left - bleed, y));
+            line_mm(content, page_height, (right + bleed, y), (right + bleed + CROP_MARK_LENGTH_MM, y));
+        }
+        for x in [left, right] {
+            line_mm(content, page_height, (x, top - bleed - CROP_MARK_LENGTH_MM), (x, top - bleed));
+            line_mm(content, page_height, (x, bottom + bleed), (x, bottom + bleed + CROP_MARK_LENGTH_MM));
+        }
+    }
+
+    content.stroke();
+    content.restore_state();
+}
+
+// The standard fonts only need their name, every PDF reader has them built in.
+const REGULAR_FONT: Name = Name(b"F1");
+const BOLD_FONT: Name = Name(b"F2");
+
+// The standard fonts use the Windows code page, which has the typographic punctuation of rules text but not the minus sign.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars().map(|character| match character {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => character as u8,
+        '…' => 0x85,
+        '•' => 0x95,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '–' => 0x96,
+        '—' => 0x97,
+        '−' => b'-',
+        _ => b'?',
+    }).collect()
+}
+
+// Text proxies are drawn as vectors on a white card, their coordinates being millimetres from the card's top left corner.
+fn draw_text_card(content: &mut Content, text_card: &TextCard, (left, top): (f32, f32), page_height: f32) {
+    content.save_state();
+    content.set_fill_gray(1.0);
+    content.rect(left * POINTS_PER_MM, (page_height - top - CARD_HEIGHT_MM) * POINTS_PER_MM, CARD_WIDTH_MM * POINTS_PER_MM, CARD_HEIGHT_MM * POINTS_PER_MM);
+    content.fill_nonzero();
+
+    content.set_stroke_gray(0.0);
+    content.set_line_width(LINE_WIDTH_MM * POINTS_PER_MM);
+    for rect in &text_card.rects {
+        let (rect_left, rect_top) = (left + rect.x_mm, top + rect.y_mm);
+        let (rect_right, rect_bottom) = (rect_left + rect.width_mm, rect_top + rect.height_mm);
+
+        line_mm(content, page_height, (rect_left, rect_top), (rect_right, rect_top));
+        content.line_to(rect_right * POINTS_PER_MM, (page_height - rect_bottom) * POINTS_PER_MM);
+        content.line_to(rect_left * POINTS_PER_MM, (page_height - rect_bottom) * POINTS_PER_MM);
+        content.close_path();
+    }
+    for line in &text_card.lines {
+        line_mm(content, page_height, (left + line.from_mm.0, top + line.from_mm.1), (left + line.to_mm.0, top + line.to_mm.1));
+    }
+    content.stroke();
+
+    content.set_fill_gray(0.0);
+    for span in &text_card.spans {
+        let offset = if span.anchor == TextAnchor::End { text_width_mm(&span.text, span.size_pt, span.weight) } else { 0.0 };
+        let font = if span.weight == FontWeight::Bold { BOLD_FONT } else { REGULAR_FONT };
+
+        content.begin_text();
+        content.set_font(font, span.size_pt);
+        content.next_line((left + span.x_mm - offset) * POINTS_PER_MM, (page_height - top - span.y_mm) * POINTS_PER_MM);
+        content.show(Str(&win_ansi(&span.text)));
+        content.end_text();
+    }
+    content.restore_state();
+}
+
+fn draw_cut_lines(content: &mut Content, layout: &PageLayout) {
+    let (page_width, page_height) = layout.page_size.dimensions_mm();
+    let (column_edges, row_edges) = layout.cut_positions_mm();
+
+    content.save_state();
+    content.set_stroke_gray(0.5);
+    content.set_line_width(CUT_LINE_WIDTH_MM * POINTS_PER_MM);
+
+    for x in column_edges {
+        line_mm(content, page_height, (x, 0.0), (x, page_height));
+    }
+    for y in row_edges {
+        line_mm(content, page_height, (0.0, y), (page_width, y));
+    }
+
+    content.stroke();
+    content.restore_state();
+}
+
+pub fn generate_proxies_pdf(pages: &[PageSlots], loaded_images: &HashMap<String, Vec<u8>>, layout: &PageLayout, guides: CutGuides, images_include_bleed: bool, text_cards: &HashMap<String, TextCard>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut pdf = Pdf::new();
+    let mut refs = RefAllocator { next: 1 };
+
+    let catalog_id = refs.next();
+    let page_tree_id = refs.next();
+
+    // Each unique image is embedded once and referenced by every copy of the card.
+    let mut image_ids: HashMap<&str, (Ref, String)> = HashMap::new();
+    for card_image in pages.iter().flatten().flatten() {
+        let source = card_image.source.as_str();
+        if image_ids.contains_key(source) || text_cards.contains_key(source) {
+            continue;
+        }
+
+        let Some(data) = loaded_images.get(source) else {
+            return Err(Box::new(MissingImageError { source: source.to_owned() }));
+        };
+
+        let image_name = format!("Im{}", image_ids.len() + 1);
+        image_ids.insert(source, (write_image(&mut pdf, &mut refs, data)?, image_name));
+    }
+
+    let font_ids = (!text_cards.is_empty()).then(|| {
+        let (regular_id, bold_id) = (refs.next(), refs.next());
+        pdf.type1_font(regular_id).base_font(Name(b"Helvetica")).encoding_predefined(Name(b"WinAnsiEncoding"));
+        pdf.type1_font(bold_id).base_font(Name(b"Helvetica-Bold")).encoding_predefined(Name(b"WinAnsiEncoding"));
+        (regular_id, bold_id)
+    });
+
+    let (page_width, page_height) = layout.page_size.dimensions_mm();
+    // Processed images already have their bleed, so they are drawn over it instead of a flat border colour.
+    let image_bleed_mm = if images_include_bleed { layout.bleed_mm } else { 0.0 };
+    let media_box = Rect::new(0.0, 0.0, page_width * POINTS_PER_MM, page_height * POINTS_PER_MM);
+
+    let mut page_ids = Vec::new();
+    for page_slots in pages {
+        let page_id = refs.next();
+        let content_id = refs.next();
+        page_ids.push(page_id);
+
+        let mut content = Content::new();
+
+        // Crop marks sit underneath the cards so they only show in the gutters and margins.
+        if guides.crop_marks {
+            draw_crop_marks(&mut content, layout, page_slots);
+        }
+
+        for (index_on_page, card_image) in page_slots.iter().enumerate() {
+            let Some(card_image) = card_image else {
+                continue;
+            };
+
+            let position = layout.card_position(index_on_page);
+
+            if layout.bleed_mm > 0.0 && !images_include_bleed {
+                let [red, green, blue] = card_image.border_rgb.map(|channel| channel as f32 / 255.0);
+
+                content.save_state();
+                content.set_fill_rgb(red, green, blue);
+                content.rect(
+                    (position.x_mm - layout.bleed_mm) * POINTS_PER_MM,
+                    (page_height - position.y_mm - CARD_HEIGHT_MM - layout.bleed_mm) * POINTS_PER_MM,
+                    (CARD_WIDTH_MM + 2.0 * layout.bleed_mm) * POINTS_PER_MM,
+                    (CARD_HEIGHT_MM + 2.0 * layout.bleed_mm) * POINTS_PER_MM,
+                );
+                content.fill_nonzero();
+                content.restore_state();
+            }
+
+            if let Some(text_card) = text_cards.get(&card_image.source) {
+                draw_text_card(&mut content, text_card, (position.x_mm, position.y_mm), page_height);
+                continue;
+            }
+
+            // PDF coordinates start at the bottom left of the page, so the card's top edge is flipped.
+            let (_, image_name) = &image_ids[card_image.source.as_str()];
+            content.save_state();
+            content.transform([
+                (CARD_WIDTH_MM + 2.0 * image_bleed_mm) * POINTS_PER_MM,
+                0.0,
+                0.0,
+                (CARD_HEIGHT_MM + 2.0 * image_bleed_mm) * POINTS_PER_MM,
+                (position.x_mm - image_bleed_mm) * POINTS_PER_MM,
+                (page_height - position.y_mm - CARD_HEIGHT_MM - image_bleed_mm) * POINTS_PER_MM,
+            ]);
+            content.x_object(Name(image_name.as_bytes()));
+            content.restore_state();
+        }
+
+        // Cut lines only cross the cards along their edges, so they are drawn on top to stay visible over the bleed.
+        if guides.cut_lines {
+            draw_cut_lines(&mut content, layout);
+        }
+
+        let mut page = pdf.page(page_id);
+        page.media_box(media_box);
+        page.parent(page_tree_id);
+        page.contents(content_id);
+
+        let mut resources = page.resources();
+        let mut x_objects = resources.x_objects();
+        let mut page_sources: Vec<&str> = page_slots.iter().flatten()
+            .map(|card_image| card_image.source.as_str())
+            .filter(|source| !text_cards.contains_key(*source))
+            .collect();
+        page_sources.sort_unstable();
+        page_sources.dedup();
+        for source in page_sources {
+            let (image_id, image_name) = &image_ids[source];
+            x_objects.pair(Name(image_name.as_bytes()), *image_id);
+        }
+        x_objects.finish();
+        if let Some((regular_id, bold_id)) = font_ids {
+            resources.fonts().pair(REGULAR_FONT, regular_id).pair(BOLD_FONT, bold_id);
+        }
+        resources.finish();
+        page.finish();
+
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).count(page_ids.len() as i32).kids(page_ids);
+
+    Ok(pdf.finish())
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, error::Error, fmt::Display, fs, path::Path};
+use image::ImageFormat;
+use log::info;
+use scryfall::xml;
+
+use crate::{image_download::ImageLoader, image_processing::ImagePipeline, page_layout::{NamedProxyCard, ProxyImage}};
+
+const DPI: f32 = 300.0;
+const MM_PER_INCH: f32 = 25.4;
+const BLEED_MM: f32 = MM_PER_INCH / 8.0;
+const ORDER_FILE_NAME: &str = "order.xml";
+const CARD_BACK_FILE_NAME: &str = "cardback.png";
+
+// MakePlayingCards prices orders by these quantities, the largest being the most cards in one order.
+const BRACKETS: [usize; 16] = [18, 36, 55, 72, 90, 108, 126, 144, 162, 180, 198, 216, 234, 396, 504, 612];
+
+#[derive(Debug, Clone)]
+pub struct OrderTooLargeError {
+    quantity: usize,
+}
+
+impl Display for OrderTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "An order holds at most {} cards, {} were given", BRACKETS[BRACKETS.len() - 1], self.quantity)
+    }
+}
+
+impl Error for OrderTooLargeError {}
+
+// Print shops want 300 DPI images with an eighth of an inch of bleed.
+pub fn print_shop_pipeline(dpi: Option<f32>, grayscale: bool) -> ImagePipeline {
+    ImagePipeline::for_print(Some(dpi.unwrap_or(DPI)), BLEED_MM, grayscale)
+}
+
+fn order_card_xml(path: &Path, slots: &[usize], query: &str) -> String {
+    let slots: Vec<String> = slots.iter().map(usize::to_string).collect();
+    let file_name = path.file_name().map(|file_name| file_name.to_string_lossy()).unwrap_or_default();
+
+    format!(
+        "        <card>\n            <id>{}</id>\n            <slots>{}</slots>\n            <name>{}</name>\n            <query>{}</query>\n        </card>\n",
+        xml::escape(&path.to_string_lossy()),
+        slots.join(","),
+        xml::escape(&file_name),
+        xml::escape(&query.to_lowercase()),
+    )
+}
+
+// Writes an image for each face and an order file that MPC Autofill uploads from, with local file paths as image IDs.
+// Each copy of a card takes its own slot, and double-faced cards have their back in the same slots as their front.
+// Cards without their own back use the common card back.
+pub async fn write_print_shop_order(directory: &Path, cards: &[NamedProxyCard], common_back: &ProxyImage, pipeline: &ImagePipeline, image_loader: &ImageLoader) -> Result<(), Box<dyn Error>> {
+    let quantity = cards.iter().map(|card| card.count).sum();
+    let Some(bracket) = BRACKETS.into_iter().find(|bracket| *bracket >= quantity) else {
+        return Err(Box::new(OrderTooLargeError { quantity }));
+    };
+
+    fs::create_dir_all(directory)?;
+    let directory = std::path::absolute(directory)?;
+
+    let proxy_images = cards.iter()
+        .flat_map(|card| [Some(&card.front), card.back.as_ref()])
+        .flatten()
+        .chain([common_back]);
+    let processed_images: HashMap<String, Vec<u8>> = pipeline.load_processed_images(proxy_images, ImageFormat::Png, image_loader).await?;
+
+    let write_image = |proxy_image: &ProxyImage, file_name: String| -> Result<_, Box<dyn Error>> {
+        let path = directory.join(file_name);
+        info!("Writing print shop image {}", path.display());
+
+        fs::write(&path, processed_images.get(&proxy_image.source).ok_or("Card image was not loaded")?)?;
+        Ok(path)
+    };
+
+    let mut fronts_xml = String::new();
+    let mut backs_xml = String::new();
+    let mut next_slot = 0;
+
+    for card in cards {
+        let slots: Vec<usize> = (next_slot..next_slot + card.count).collect();
+        next_slot += card.count;
+
+        let front_path = write_image(&card.front, format!("{}-front.png", card.file_stem))?;
+        fronts_xml += &order_card_xml(&front_path, &slots, &card.name);
+
+        if let Some(back) = &card.back {
+            let back_path = write_image(back, format!("{}-back.png", card.file_stem))?;
+            backs_xml += &order_card_xml(&back_path, &slots, &card.name);
+        }
+    }
+
+    let card_back_path = write_image(common_back, CARD_BACK_FILE_NAME.to_owned())?;
+
+    let order_xml = format!(
+        "<order>\n    <details>\n        <quantity>{quantity}</quantity>\n        <bracket>{bracket}</bracket>\n        <stock>(S30) Standard Smooth</stock>\n        <foil>false</foil>\n    </details>\n    <fronts>\n{fronts_xml}    </fronts>\n    <backs>\n{backs_xml}    </backs>\n    <cardback>{}</cardback>\n</order>\n",
+        xml::escape(&card_back_path.to_string_lossy()),
+    );
+    fs::write(directory.join(ORDER_FILE_NAME), order_xml)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbaImage};
+    use super::*;
+    use crate::page_layout::{CARD_HEIGHT_MM, CARD_WIDTH_MM};
+
+    #[test]
+    fn test_print_shop_image_size() {
+        let card_image = DynamicImage::ImageRgba8(RgbaImage::new(488, 680));
+        let processed = print_shop_pipeline(None, false).apply(card_image, [0, 0, 0]);
+
+        // The card and its eighth of an inch of bleed on each side are rounded to whole pixels separately.
+        let pixels = |mm: f32| (mm / MM_PER_INCH * DPI).round() as u32;
+        let bleed = (BLEED_MM * pixels(CARD_WIDTH_MM) as f32 / CARD_WIDTH_MM).round() as u32;
+        assert_eq!(processed.dimensions(), (pixels(CARD_WIDTH_MM) + 2 * bleed, pixels(CARD_HEIGHT_MM) + 2 * bleed));
+        assert_eq!(processed.dimensions(), (818, 1113));
+    }
+
+    #[test]
+    fn test_order_card_xml_escaping() {
+        let card_xml = order_card_xml(Path::new("cards/kongming-1-front.png"), &[0, 1], "Kongming, \"Sleeping Dragon\" & Friends");
+        assert!(card_xml.contains("<slots>0,1</slots>"));
+        assert!(card_xml.contains("<query>kongming, &quot;sleeping dragon&quot; &amp; friends</query>"));
+    }
+}
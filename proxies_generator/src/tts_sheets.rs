@@ -0,0 +1,89 @@
+use std::{error::Error, fmt::Display, io::Cursor, path::Path};
+use image::{codecs::jpeg::JpegEncoder, imageops::{self, FilterType}, DynamicImage, RgbImage};
+use scryfall::tabletop_simulator::{CARDS_PER_SHEET, SHEET_COLUMNS, SHEET_ROWS};
+
+const JPEG_QUALITY: u8 = 90;
+
+#[derive(Debug, Clone)]
+pub struct TooManyImagesError {
+    count: usize,
+}
+
+impl Display for TooManyImagesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A contact sheet holds at most {CARDS_PER_SHEET} images, {} were given", self.count)
+    }
+}
+
+impl Error for TooManyImagesError {}
+
+// Lays the images out row by row from the top left, each scaled to the size of the first one.
+pub fn stitch_contact_sheet(images: &[&[u8]]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if images.len() > CARDS_PER_SHEET {
+        return Err(Box::new(TooManyImagesError { count: images.len() }));
+    }
+
+    let mut sheet: Option<RgbImage> = None;
+
+    for (index, data) in images.iter().enumerate() {
+        let card_image = image::load_from_memory(data)?.to_rgb8();
+        let sheet = sheet.get_or_insert_with(|| RgbImage::new(card_image.width() * SHEET_COLUMNS as u32, card_image.height() * SHEET_ROWS as u32));
+        let (cell_width, cell_height) = (sheet.width() / SHEET_COLUMNS as u32, sheet.height() / SHEET_ROWS as u32);
+
+        let card_image = if card_image.dimensions() == (cell_width, cell_height) {
+            card_image
+        } else {
+            imageops::resize(&card_image, cell_width, cell_height, FilterType::Triangle)
+        };
+
+        let (column, row) = (index % SHEET_COLUMNS, index / SHEET_COLUMNS);
+        imageops::replace(sheet, &card_image, (column as u32 * cell_width).into(), (row as u32 * cell_height).into());
+    }
+
+    let mut encoded = Cursor::new(Vec::new());
+    JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY).encode_image(&DynamicImage::ImageRgb8(sheet.unwrap_or_default()))?;
+
+    Ok(encoded.into_inner())
+}
+
+// Tabletop Simulator loads local images from file URLs, which need absolute paths with forward slashes.
+pub fn file_url(path: &Path) -> Result<String, Box<dyn Error>> {
+    let absolute_path = std::path::absolute(path)?.to_string_lossy().replace('\\', "/");
+
+    if absolute_path.starts_with('/') {
+        Ok(format!("file://{absolute_path}"))
+    } else {
+        Ok(format!("file:///{absolute_path}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, Rgb};
+    use super::*;
+
+    fn encoded_image(width: u32, height: u32, colour: [u8; 3]) -> Vec<u8> {
+        let mut encoded = Cursor::new(Vec::new());
+        RgbImage::from_pixel(width, height, Rgb(colour)).write_to(&mut encoded, ImageFormat::Png).expect("Test image should encode");
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn test_stitch_contact_sheet() {
+        let red = encoded_image(20, 28, [255, 0, 0]);
+        let blue = encoded_image(40, 56, [0, 0, 255]);
+        let images: Vec<&[u8]> = [&red, &blue].into_iter().cycle().take(12).map(Vec::as_slice).collect();
+
+        let sheet = image::load_from_memory(&stitch_contact_sheet(&images).expect("Images should stitch")).expect("Sheet should decode").to_rgb8();
+        assert_eq!(sheet.dimensions(), (200, 196));
+
+        // Centres of the first, second and eleventh cells, the last being the first of the second row.
+        let [first, second, eleventh] = [(10, 14), (30, 14), (10, 42)].map(|(x, y)| sheet.get_pixel(x, y).0);
+        assert!(first[0] > 200 && first[2] < 50);
+        assert!(second[2] > 200 && second[0] < 50);
+        assert!(eleventh[0] > 200 && eleventh[2] < 50);
+
+        let too_many = vec![red.as_slice(); CARDS_PER_SHEET + 1];
+        assert!(stitch_contact_sheet(&too_many).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::{error::Error, fmt::Display};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const LOCAL_HEADER_LENGTH: usize = 30;
+// Version 2.0, the first to support folders, which is what every reader expects at least.
+const ZIP_VERSION: u16 = 20;
+// File names are UTF-8.
+const UTF8_FLAG: u16 = 1 << 11;
+// Midnight on the 1st of January 1980, the earliest time ZIP archives can hold.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+#[derive(Debug, Clone)]
+pub struct ArchiveTooLargeError;
+
+impl Display for ArchiveTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ZIP archives without the 64-bit extensions are limited to 4GB and 65535 files")
+    }
+}
+
+impl Error for ArchiveTooLargeError {}
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+// Writes files without compression, as card images are already compressed.
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<CentralEntry>,
+}
+
+impl ZipWriter {
+    pub fn add_file(&mut self, name: &str, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+        let size = u32::try_from(contents.len()).map_err(|_| ArchiveTooLargeError)?;
+        let offset = u32::try_from(self.data.len()).map_err(|_| ArchiveTooLargeError)?;
+        let crc = crc32fast::hash(contents);
+
+        self.data.extend(LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        self.data.extend(ZIP_VERSION.to_le_bytes());
+        self.data.extend(UTF8_FLAG.to_le_bytes());
+        // Stored, without compression.
+        self.data.extend(0_u16.to_le_bytes());
+        self.data.extend(DOS_TIME.to_le_bytes());
+        self.data.extend(DOS_DATE.to_le_bytes());
+        self.data.extend(crc.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend((name.len() as u16).to_le_bytes());
+        self.data.extend(0_u16.to_le_bytes());
+        self.data.extend(name.as_bytes());
+        self.data.extend(contents);
+
+        self.entries.push(CentralEntry { name: name.to_owned(), crc, size, offset });
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let directory_offset = u32::try_from(self.data.len()).map_err(|_| ArchiveTooLargeError)?;
+        let entry_count = u16::try_from(self.entries.len()).map_err(|_| ArchiveTooLargeError)?;
+
+        for entry in &self.entries {
+            self.data.extend(CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            self.data.extend(ZIP_VERSION.to_le_bytes());
+            self.data.extend(ZIP_VERSION.to_le_bytes());
+            self.data.extend(UTF8_FLAG.to_le_bytes());
+            self.data.extend(0_u16.to_le_bytes());
+            self.data.extend(DOS_TIME.to_le_bytes());
+            self.data.extend(DOS_DATE.to_le_bytes());
+            self.data.extend(entry.crc.to_le_bytes());
+            self.data.extend(entry.size.to_le_bytes());
+            self.data.extend(entry.size.to_le_bytes());
+            self.data.extend((entry.name.len() as u16).to_le_bytes());
+            // Extra field, comment, disk number, internal and external attributes.
+            self.data.extend([0; 12]);
+            self.data.extend(entry.offset.to_le_bytes());
+            self.data.extend(entry.name.as_bytes());
+        }
+
+        let directory_size = self.data.len() as u32 - directory_offset;
+        self.data.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        self.data.extend([0; 4]);
+        self.data.extend(entry_count.to_le_bytes());
+        self.data.extend(entry_count.to_le_bytes());
+        self.data.extend(directory_size.to_le_bytes());
+        self.data.extend(directory_offset.to_le_bytes());
+        self.data.extend(0_u16.to_le_bytes());
+
+        Ok(self.data)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// Reads back the stored files of an archive written by ZipWriter, stopping at the first entry it cannot read.
+// Files whose contents do not match their checksum are left out, so they are downloaded again.
+pub fn read_stored_files(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut files = Vec::new();
+    let mut offset = 0;
+
+    while read_u32(data, offset) == Some(LOCAL_HEADER_SIGNATURE) {
+        let (Some(method), Some(crc), Some(compressed_size), Some(size), Some(name_length), Some(extra_length)) = (
+            read_u16(data, offset + 8),
+            read_u32(data, offset + 14),
+            read_u32(data, offset + 18),
+            read_u32(data, offset + 22),
+            read_u16(data, offset + 26),
+            read_u16(data, offset + 28),
+        ) else {
+            break;
+        };
+
+        if method != 0 || compressed_size != size {
+            break;
+        }
+
+        let name_start = offset + LOCAL_HEADER_LENGTH;
+        let contents_start = name_start + name_length as usize + extra_length as usize;
+        let (Some(name), Some(contents)) = (data.get(name_start..name_start + name_length as usize), data.get(contents_start..contents_start + size as usize)) else {
+            break;
+        };
+
+        if crc32fast::hash(contents) == crc {
+            files.push((String::from_utf8_lossy(name).into_owned(), contents.to_vec()));
+        }
+        offset = contents_start + size as usize;
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zip_round_trip() {
+        let mut writer = ZipWriter::default();
+        writer.add_file("xln-65-front.jpg", b"front image").expect("File should fit");
+        writer.add_file("manifest.csv", b"File,Count\n").expect("File should fit");
+        let mut archive = writer.finish().expect("Archive should fit");
+
+        assert_eq!(read_u32(&archive, archive.len() - 22), Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE));
+        assert_eq!(read_u16(&archive, archive.len() - 12), Some(2));
+        assert_eq!(read_stored_files(&archive), vec![
+            ("xln-65-front.jpg".to_owned(), b"front image".to_vec()),
+            ("manifest.csv".to_owned(), b"File,Count\n".to_vec()),
+        ]);
+
+        // A damaged file is left out, the others are still read.
+        archive[LOCAL_HEADER_LENGTH + "xln-65-front.jpg".len()] = b'F';
+        assert_eq!(read_stored_files(&archive), vec![("manifest.csv".to_owned(), b"File,Count\n".to_vec())]);
+    }
+}
@@ -1,20 +1,31 @@
 pub mod collection_card_identifier;
 pub mod api_classes;
+pub mod client_settings;
+pub mod fixture_clients;
+pub mod rate_limiter;
+pub mod request_keys;
+pub mod retry_policy;
 #[cfg(feature = "std")]
 pub mod reqwest_wrapper;
+#[cfg(feature = "std")]
+pub mod bulk_data_client;
+#[cfg(feature = "std")]
+pub mod caching_client;
 #[cfg(feature = "wasm")]
 pub mod wasm_fetch_wrapper;
 
-use core::{error::Error as ErrorTrait, fmt::Display, future::Future};
+use core::{error::Error as ErrorTrait, fmt::Display, future::Future, marker::PhantomData, time::Duration};
 use alloc::{borrow::ToOwned, boxed::Box, format, string::String, vec::Vec};
 use log::{info, warn};
 use serde_json::{from_str, json, Value};
 
 use api_classes::{ApiObject, Card, Error};
+use client_settings::{ClientSettings, DEFAULT_API_ENDPOINT};
 use collection_card_identifier::CollectionCardIdentifier;
+use retry_policy::RetryPolicy;
 
 pub trait RequestClient {
-    fn build() -> Result<Self, Box<dyn ErrorTrait>>
+    fn build(settings: &ClientSettings) -> Result<Self, Box<dyn ErrorTrait>>
         where Self: Sized;
 
     fn get(&self, url: String) -> impl Future<Output = Result<String, Box<dyn ErrorTrait>>>;
@@ -68,17 +79,97 @@ static CARD_COLLECTION_METHOD: &str = "cards/collection";
 
 pub struct ApiInterface<Client>
     where Client: RequestClient {
-    http_client: Client,
+    pub(crate) http_client: Client,
+    api_endpoint: String,
+}
+
+// The client type is usually inferred from where the built interface is used.
+pub struct ApiInterfaceBuilder<Client>
+    where Client: RequestClient {
     api_endpoint: String,
+    settings: ClientSettings,
+    client: PhantomData<Client>,
+}
+
+impl<Client> Default for ApiInterfaceBuilder<Client>
+    where Client: RequestClient {
+    fn default() -> Self {
+        Self {
+            api_endpoint: DEFAULT_API_ENDPOINT.to_owned(),
+            settings: ClientSettings::default(),
+            client: PhantomData,
+        }
+    }
+}
+
+impl<Client> ApiInterfaceBuilder<Client>
+    where Client: RequestClient {
+    // Base URL of the API, such as a local mirror or mock server, without a trailing slash.
+    pub fn endpoint(mut self, api_endpoint: &str) -> Self {
+        self.api_endpoint = api_endpoint.trim_end_matches('/').to_owned();
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.settings.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.settings.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.settings.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    pub fn requests_per_second(mut self, requests_per_second: u32) -> Self {
+        self.settings.requests_per_second = requests_per_second;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.settings.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn settings(&self) -> &ClientSettings {
+        &self.settings
+    }
+
+    pub fn build(self) -> Result<ApiInterface<Client>, Box<dyn ErrorTrait>> {
+        let http_client = Client::build(&self.settings)?;
+        Ok(self.build_with_client(http_client))
+    }
+
+    // For clients that need more than the settings to be built, the settings can be applied to them beforehand.
+    pub fn build_with_client(self, http_client: Client) -> ApiInterface<Client> {
+        ApiInterface {
+            http_client,
+            api_endpoint: self.api_endpoint,
+        }
+    }
 }
 
 impl<Client> ApiInterface<Client>
     where Client: RequestClient {
+    pub fn builder() -> ApiInterfaceBuilder<Client> {
+        ApiInterfaceBuilder::default()
+    }
+
     pub fn new() -> Result<Self, Box<dyn ErrorTrait>> {
-        Ok(Self {
-            http_client: Client::build()?,
-            api_endpoint: "https://api.scryfall.com".to_owned(),
-        })
+        Self::builder().build()
+    }
+
+    pub fn from_client(http_client: Client) -> Self {
+        Self::builder().build_with_client(http_client)
     }
 
     pub async fn get_card(&self, card: &CollectionCardIdentifier) -> Result<ApiObject, Box<dyn ErrorTrait>> {
@@ -132,10 +223,18 @@ impl<Client> ApiInterface<Client>
         }
     }
 
+    // The API links to further pages and printings on its own host, which a configured endpoint such as a mirror replaces.
+    fn on_endpoint(&self, url: String) -> String {
+        match url.strip_prefix(DEFAULT_API_ENDPOINT) {
+            Some(path) if path.is_empty() || path.starts_with(['/', '?']) => format!("{}{path}", self.api_endpoint),
+            _ => url,
+        }
+    }
+
     async fn resolve_multi_page_search(&self, search_url: String) -> Result<Vec<ApiObject>, Box<dyn ErrorTrait>> {
         info!("Sending API request for next page of results");
 
-        let response = self.http_client.get(search_url).await?;
+        let response = self.http_client.get(self.on_endpoint(search_url)).await?;
 
         let api_object = from_str(&response)?;
         if let ApiObject::Error(error) = api_object {
@@ -179,4 +278,41 @@ impl<Client> ApiInterface<Client>
 
         Ok(card_printings)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use super::*;
+    use crate::{api_interface::fixture_clients::ReplayClient, test_cards::test_card_json};
+
+    #[test]
+    fn test_custom_endpoint() {
+        let replay_client = ReplayClient::default()
+            .with_response("GET http://localhost:8080/cards/named?fuzzy=Opt".to_owned(), test_card_json(1, "Opt", "XLN", "65"));
+        let interface = ApiInterface::builder().endpoint("http://localhost:8080/").build_with_client(replay_client);
+
+        let card = block_on(interface.get_card(&CollectionCardIdentifier::Name { name: "Opt".to_owned() })).expect("Card should be requested from the custom endpoint");
+        let ApiObject::Card(card) = card else {
+            panic!("Expected a card, found {card}");
+        };
+        assert_eq!(card.name, "Opt");
+    }
+
+    #[test]
+    fn test_custom_endpoint_printings() {
+        let page = |card: Value, next_page: Option<&str>| json!({"object": "list", "has_more": next_page.is_some(), "next_page": next_page, "data": [card]});
+        let replay_client = ReplayClient::default()
+            .with_response("GET http://localhost:8080/cards/search?q=oracleid%3A1&unique=prints".to_owned(), page(test_card_json(1, "Opt", "XLN", "65"), Some("https://api.scryfall.com/cards/search?page=2&q=oracleid%3A1&unique=prints")))
+            .with_response("GET http://localhost:8080/cards/search?page=2&q=oracleid%3A1&unique=prints".to_owned(), page(test_card_json(2, "Opt", "DOM", "60"), None));
+        let interface = ApiInterface::builder().endpoint("http://localhost:8080").build_with_client(replay_client);
+
+        // Both the printings search and the page it links to are requested from the custom endpoint, not from Scryfall.
+        let printings = block_on(interface.get_all_printings("https://api.scryfall.com/cards/search?q=oracleid%3A1&unique=prints".to_owned(), "Opt".to_owned()))
+            .expect("Printings should be requested from the custom endpoint");
+        assert_eq!(printings.iter().map(|card| card.set.as_str()).collect::<Vec<&str>>(), ["xln", "dom"]);
+
+        // Other hosts are left alone.
+        assert_eq!(interface.on_endpoint("https://api.scryfall.com.example/cards".to_owned()), "https://api.scryfall.com.example/cards");
+    }
+}
@@ -131,7 +131,8 @@ pub struct Card {
     pub color_indicator: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub colors: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    // The API spells it the American way.
+    #[serde(alias = "defense", skip_serializing_if = "Option::is_none")]
     pub defence: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub edhrec_rank: Option<usize>,
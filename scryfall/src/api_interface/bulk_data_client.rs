@@ -0,0 +1,423 @@
+use std::{error::Error, fmt::Display, fs::File, io::BufReader, path::Path};
+use hashbrown::HashMap;
+use log::info;
+use reqwest::Url;
+use serde_json::{from_value, to_string, Value};
+use uuid::Uuid;
+
+use super::{api_classes::{self, ApiObject, Card, List}, client_settings::ClientSettings, collection_card_identifier::CollectionCardIdentifier, RequestClient};
+
+#[derive(Debug, Clone)]
+pub struct BulkDataPathRequiredError;
+
+impl Display for BulkDataPathRequiredError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The bulk data client must be created from a bulk data file")
+    }
+}
+
+impl Error for BulkDataPathRequiredError {}
+
+// Answers API requests from a Scryfall bulk data file (default_cards or all_cards) instead of the network.
+pub struct BulkDataClient {
+    cards: Vec<Card>,
+    by_id: HashMap<Uuid, usize>,
+    by_collector_number_set: HashMap<(String, String), usize>,
+    by_mtgo_id: HashMap<usize, usize>,
+    by_multiverse_id: HashMap<usize, usize>,
+    by_oracle_id: HashMap<Uuid, Vec<usize>>,
+    by_illustration_id: HashMap<Uuid, Vec<usize>>,
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+// Scryfall ignores case and punctuation in fuzzy searches.
+fn normalise_name(name: &str) -> String {
+    name.chars()
+        .filter_map(|character| {
+            if character.is_alphanumeric() {
+                Some(character.to_lowercase().next().unwrap_or(character))
+            } else if character.is_whitespace() || character == '/' {
+                Some(' ')
+            } else {
+                None
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=right.len()).collect();
+
+    for (left_index, left_char) in left.chars().enumerate() {
+        let mut current_row = vec![left_index + 1];
+
+        for (right_index, right_char) in right.iter().enumerate() {
+            let substitution = previous_row[right_index] + usize::from(left_char != *right_char);
+            current_row.push(substitution.min(previous_row[right_index + 1] + 1).min(current_row[right_index] + 1));
+        }
+
+        previous_row = current_row;
+    }
+
+    previous_row[right.len()]
+}
+
+fn not_found_error(details: String) -> ApiObject {
+    ApiObject::Error(Box::new(api_classes::Error {
+        status: 404,
+        code: "not_found".to_owned(),
+        details,
+        error_type: None,
+        warnings: None,
+    }))
+}
+
+fn bad_request_error(details: String) -> ApiObject {
+    ApiObject::Error(Box::new(api_classes::Error {
+        status: 400,
+        code: "bad_request".to_owned(),
+        details,
+        error_type: None,
+        warnings: None,
+    }))
+}
+
+fn list_of(data: Vec<ApiObject>, not_found: Option<Vec<CollectionCardIdentifier>>) -> ApiObject {
+    let total_cards = data.len();
+
+    ApiObject::List(Box::new(List {
+        data,
+        not_found,
+        has_more: Some(false),
+        next_page: None,
+        total_cards: Some(total_cards),
+        warnings: None,
+    }))
+}
+
+impl BulkDataClient {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        info!("Loading bulk data from {}", path.as_ref().display());
+
+        let cards: Vec<Card> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(Self::from_cards(cards))
+    }
+
+    pub fn from_cards(cards: Vec<Card>) -> Self {
+        let mut client = Self {
+            cards: Vec::new(),
+            by_id: HashMap::new(),
+            by_collector_number_set: HashMap::new(),
+            by_mtgo_id: HashMap::new(),
+            by_multiverse_id: HashMap::new(),
+            by_oracle_id: HashMap::new(),
+            by_illustration_id: HashMap::new(),
+            by_name: HashMap::new(),
+        };
+
+        for (index, card) in cards.iter().enumerate() {
+            client.by_id.insert(card.id, index);
+
+            // The all cards file has every language of a printing, of which the API returns the English one.
+            let printing = client.by_collector_number_set.entry((card.set.to_ascii_lowercase(), card.collector_number.to_ascii_lowercase())).or_insert(index);
+            if card.lang == "en" {
+                *printing = index;
+            }
+
+            for mtgo_id in [card.mtgo_id, card.mtgo_foil_id].into_iter().flatten() {
+                client.by_mtgo_id.insert(mtgo_id, index);
+            }
+            for multiverse_id in card.multiverse_ids.iter().flatten() {
+                client.by_multiverse_id.insert(*multiverse_id, index);
+            }
+
+            let oracle_id = card.oracle_id.or_else(|| card.card_faces.as_ref().and_then(|faces| faces.first()?.oracle_id));
+            if let Some(oracle_id) = oracle_id {
+                client.by_oracle_id.entry(oracle_id).or_default().push(index);
+            }
+            if let Some(illustration_id) = card.illustration_id {
+                client.by_illustration_id.entry(illustration_id).or_default().push(index);
+            }
+
+            // Cards can be found by their full name or the name of any of their faces.
+            let mut names = vec![normalise_name(&card.name)];
+            for face in card.card_faces.iter().flatten() {
+                names.push(normalise_name(&face.name));
+            }
+            names.dedup();
+            for name in names {
+                client.by_name.entry(name).or_default().push(index);
+            }
+        }
+
+        client.cards = cards;
+        client
+    }
+
+    // Approximates Scryfall's choice of default printing: English paper printings first, then the most recent.
+    fn default_printing(&self, candidates: &[usize], set: Option<&str>) -> Option<&Card> {
+        candidates.iter()
+            .map(|index| &self.cards[*index])
+            .filter(|card| set.is_none_or(|set| card.set.eq_ignore_ascii_case(set)))
+            .max_by(|left, right| {
+                let rank = |card: &Card| (card.lang == "en", !card.digital, !card.oversized, !card.promo, !card.variation);
+                rank(left).cmp(&rank(right)).then_with(|| left.released_at.cmp(&right.released_at))
+            })
+    }
+
+    fn find_exact_name(&self, name: &str, set: Option<&str>) -> Option<&Card> {
+        self.default_printing(self.by_name.get(&normalise_name(name))?, set)
+    }
+
+    fn find_fuzzy_name(&self, name: &str, set: Option<&str>) -> Option<&Card> {
+        if let Some(card) = self.find_exact_name(name, set) {
+            return Some(card);
+        }
+
+        let query = normalise_name(name);
+        let query_words: Vec<&str> = query.split(' ').collect();
+
+        // Partial words are accepted as long as every word of the query appears in the name, in order.
+        let contains_words = |candidate: &str| {
+            let mut remaining = candidate;
+            query_words.iter().all(|word| match remaining.find(word) {
+                Some(position) => {
+                    remaining = &remaining[position + word.len()..];
+                    true
+                },
+                None => false,
+            })
+        };
+
+        let matching_names = self.by_name.iter()
+            .filter(|(candidate, candidates)| contains_words(candidate) && self.default_printing(candidates, set).is_some())
+            .min_by_key(|(candidate, _)| (candidate.len(), candidate.as_str()));
+
+        if let Some((_, candidates)) = matching_names {
+            return self.default_printing(candidates, set);
+        }
+
+        // Otherwise fall back to the closest name, allowing for typos.
+        let max_distance = (query.len() / 4).max(2);
+        let (_, _, candidates) = self.by_name.iter()
+            .filter(|(_, candidates)| self.default_printing(candidates, set).is_some())
+            .map(|(candidate, candidates)| (edit_distance(&query, candidate), candidate, candidates))
+            .filter(|(distance, _, _)| *distance <= max_distance)
+            .min_by_key(|(distance, candidate, _)| (*distance, candidate.as_str()))?;
+
+        self.default_printing(candidates, set)
+    }
+
+    fn find_identifier(&self, identifier: &CollectionCardIdentifier) -> Option<&Card> {
+        match identifier {
+            CollectionCardIdentifier::Id { id } => self.by_id.get(id).map(|index| &self.cards[*index]),
+            CollectionCardIdentifier::MtgoId { mtgo_id } => self.by_mtgo_id.get(mtgo_id).map(|index| &self.cards[*index]),
+            CollectionCardIdentifier::MultiverseId { multiverse_id } => self.by_multiverse_id.get(multiverse_id).map(|index| &self.cards[*index]),
+            CollectionCardIdentifier::OracleId { oracle_id } => self.default_printing(self.by_oracle_id.get(oracle_id)?, None),
+            CollectionCardIdentifier::IllustrationId { illustration_id } => self.default_printing(self.by_illustration_id.get(illustration_id)?, None),
+            CollectionCardIdentifier::Name { name } => self.find_exact_name(name, None),
+            CollectionCardIdentifier::NameSet { name, set } => self.find_exact_name(name, Some(set)),
+            CollectionCardIdentifier::CollectorNumberSet { collector_number, set } => {
+                self.by_collector_number_set.get(&(set.to_ascii_lowercase(), collector_number.to_ascii_lowercase())).map(|index| &self.cards[*index])
+            },
+        }
+    }
+
+    fn card_or_not_found(card: Option<&Card>, description: &str) -> ApiObject {
+        match card {
+            Some(card) => ApiObject::Card(Box::new(card.clone())),
+            None => not_found_error(format!("No card found matching {description}")),
+        }
+    }
+
+    fn answer_get(&self, url: &Url, query_parameters: &[(&str, &str)]) -> ApiObject {
+        let segments: Vec<&str> = url.path_segments().map(|segments| segments.filter(|segment| !segment.is_empty()).collect()).unwrap_or_default();
+        let parameter = |name: &str| {
+            query_parameters.iter()
+                .find(|(parameter_name, _)| *parameter_name == name)
+                .map(|(_, value)| (*value).to_owned())
+                .or_else(|| url.query_pairs().find(|(parameter_name, _)| parameter_name == name).map(|(_, value)| value.into_owned()))
+        };
+
+        match segments.as_slice() {
+            ["cards", "named"] => {
+                let set = parameter("set");
+                if let Some(name) = parameter("exact") {
+                    Self::card_or_not_found(self.find_exact_name(&name, set.as_deref()), &name)
+                } else if let Some(name) = parameter("fuzzy") {
+                    Self::card_or_not_found(self.find_fuzzy_name(&name, set.as_deref()), &name)
+                } else {
+                    bad_request_error("Either exact or fuzzy must be provided".to_owned())
+                }
+            },
+            // Only the oracle ID searches used by prints_search_uri are supported.
+            ["cards", "search"] => {
+                let query = parameter("q").unwrap_or_default();
+                let Some(oracle_id) = query.strip_prefix("oracleid:").and_then(|oracle_id| Uuid::parse_str(oracle_id).ok()) else {
+                    return bad_request_error(format!("Unsupported offline search query {query}"));
+                };
+
+                let mut printings: Vec<&Card> = self.by_oracle_id.get(&oracle_id).into_iter().flatten().map(|index| &self.cards[*index]).collect();
+                if printings.is_empty() {
+                    return not_found_error(format!("No cards found matching {query}"));
+                }
+
+                printings.sort_by(|left, right| right.released_at.cmp(&left.released_at));
+                list_of(printings.into_iter().map(|card| ApiObject::Card(Box::new(card.clone()))).collect(), None)
+            },
+            ["cards", "mtgo", mtgo_id] => {
+                let card = mtgo_id.parse().ok().and_then(|mtgo_id| self.find_identifier(&CollectionCardIdentifier::MtgoId { mtgo_id }));
+                Self::card_or_not_found(card, mtgo_id)
+            },
+            ["cards", "multiverse", multiverse_id] => {
+                let card = multiverse_id.parse().ok().and_then(|multiverse_id| self.find_identifier(&CollectionCardIdentifier::MultiverseId { multiverse_id }));
+                Self::card_or_not_found(card, multiverse_id)
+            },
+            ["cards", id] => {
+                let card = Uuid::parse_str(id).ok().and_then(|id| self.find_identifier(&CollectionCardIdentifier::Id { id }));
+                Self::card_or_not_found(card, id)
+            },
+            ["cards", set, collector_number] => {
+                let identifier = CollectionCardIdentifier::CollectorNumberSet { collector_number: (*collector_number).to_owned(), set: (*set).to_owned() };
+                Self::card_or_not_found(self.find_identifier(&identifier), &identifier.to_string())
+            },
+            _ => not_found_error(format!("Offline bulk data does not support {}", url.path())),
+        }
+    }
+
+    fn answer_collection(&self, payload: &Value) -> ApiObject {
+        let Some(identifiers) = payload.get("identifiers").and_then(Value::as_array) else {
+            return bad_request_error("The collection request must contain a list of identifiers".to_owned());
+        };
+
+        let mut data = Vec::new();
+        let mut not_found = Vec::new();
+
+        for identifier_json in identifiers {
+            let Ok(identifier) = from_value::<CollectionCardIdentifier>(identifier_json.clone()) else {
+                return bad_request_error(format!("Invalid card identifier {identifier_json}"));
+            };
+
+            match self.find_identifier(&identifier) {
+                Some(card) => data.push(ApiObject::Card(Box::new(card.clone()))),
+                None => not_found.push(identifier),
+            }
+        }
+
+        list_of(data, Some(not_found))
+    }
+}
+
+impl RequestClient for BulkDataClient {
+    fn build(_settings: &ClientSettings) -> Result<BulkDataClient, Box<dyn Error>> {
+        Err(Box::new(BulkDataPathRequiredError))
+    }
+
+    async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
+        self.get_with_parameters(url, &[]).await
+    }
+
+    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        let url = Url::parse(&url)?;
+        Ok(to_string(&self.answer_get(&url, query_parameters))?)
+    }
+
+    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
+        let url = Url::parse(&url)?;
+
+        let response = if url.path().trim_end_matches('/').ends_with("cards/collection") {
+            self.answer_collection(payload)
+        } else {
+            not_found_error(format!("Offline bulk data does not support {}", url.path()))
+        };
+
+        Ok(to_string(&response)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use super::*;
+    use crate::{api_interface::ApiInterface, deck_list::{DeckList, DeckListEntry}, fetch_card_data::fetch_deck::FetchDeck, test_cards::{test_card, test_card_json}};
+
+    fn test_interface() -> ApiInterface<BulkDataClient> {
+        ApiInterface::from_client(BulkDataClient::from_cards(vec![
+            test_card(1, "Opt", "XLN", "65"),
+            test_card(2, "Opt", "DOM", "60"),
+            test_card(3, "Lightning Bolt", "M10", "146"),
+            test_card(4, "Jace Beleren", "M10", "58"),
+        ]))
+    }
+
+    #[test]
+    fn test_bulk_data_collection() {
+        let interface = test_interface();
+
+        let identifiers = [
+            CollectionCardIdentifier::CollectorNumberSet { collector_number: "60".to_owned(), set: "dom".to_owned() },
+            CollectionCardIdentifier::NameSet { name: "opt".to_owned(), set: "XLN".to_owned() },
+            CollectionCardIdentifier::Name { name: "Lightning Bolt".to_owned() },
+            CollectionCardIdentifier::Name { name: "Lightnig Bolt".to_owned() },
+            CollectionCardIdentifier::NameSet { name: "Opt".to_owned(), set: "M10".to_owned() },
+        ];
+
+        let ApiObject::List(list) = block_on(interface.get_cards_from_list(&identifiers)).expect("Collection request should succeed") else {
+            panic!("Collection request should return a list");
+        };
+
+        let found: Vec<Uuid> = list.data.iter().map(|object| match object {
+            ApiObject::Card(card) => card.id,
+            other => panic!("Expected a card, found {other}"),
+        }).collect();
+        assert_eq!(found, vec![Uuid::from_u128(2), Uuid::from_u128(1), Uuid::from_u128(3)]);
+        assert_eq!(list.not_found, Some(identifiers[3..].to_vec()));
+    }
+
+    #[test]
+    fn test_bulk_data_prefers_english_printing() {
+        let translated_card = |id: u128, lang: &str| {
+            let mut card_json = test_card_json(id, "Opt", "XLN", "65");
+            card_json["lang"] = lang.into();
+            serde_json::from_value::<Card>(card_json).expect("Test card JSON should be a valid card")
+        };
+
+        // Languages are in no particular order in the all cards file, so the English printing is found either side of the others.
+        for cards in [vec![translated_card(1, "en"), translated_card(2, "ja")], vec![translated_card(2, "de"), translated_card(1, "en"), translated_card(3, "ja")]] {
+            let interface = ApiInterface::from_client(BulkDataClient::from_cards(cards));
+            let identifiers = [CollectionCardIdentifier::CollectorNumberSet { collector_number: "65".to_owned(), set: "xln".to_owned() }];
+
+            let ApiObject::List(list) = block_on(interface.get_cards_from_list(&identifiers)).expect("Collection request should succeed") else {
+                panic!("Collection request should return a list");
+            };
+            let ApiObject::Card(card) = &list.data[0] else {
+                panic!("Expected a card, found {}", list.data[0]);
+            };
+            assert_eq!(card.lang, "en");
+            assert_eq!(card.id, Uuid::from_u128(1));
+        }
+    }
+
+    #[test]
+    fn test_bulk_data_fuzzy_fallback() {
+        let interface = test_interface();
+
+        let deck_list = DeckList::from_iter([
+            DeckListEntry::new(CollectionCardIdentifier::Name { name: "jac bele".to_owned() }, 1),
+            DeckListEntry::new(CollectionCardIdentifier::Name { name: "Lightnig Bolt".to_owned() }, 4),
+        ]);
+
+        let mut resolved = block_on(interface.fetch_deck(&deck_list, false)).expect("Deck should resolve offline");
+        resolved.sort();
+
+        assert_eq!(resolved.iter().map(|card| card.card.name.as_str()).collect::<Vec<&str>>(), vec!["Jace Beleren", "Lightning Bolt"]);
+
+        let printings = block_on(interface.get_all_printings(test_card(1, "Opt", "XLN", "65").prints_search_uri, "Opt".to_owned()))
+            .expect("Printings should resolve offline");
+        assert_eq!(printings.len(), 2);
+    }
+}
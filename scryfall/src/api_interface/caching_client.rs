@@ -0,0 +1,345 @@
+use std::{error::Error, fs, io::ErrorKind, path::{Path, PathBuf}, sync::atomic::{AtomicU64, Ordering}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json, to_string, Value};
+
+use super::{client_settings::ClientSettings, request_keys::{collection_entry_key, collection_identifiers, describe_request, is_error_response, join_collection_entries, split_collection_response, CollectionEntry}, RequestClient};
+
+// The cache directory can be one the user keeps other files in, so only files with this extension are ever removed.
+const CACHE_FILE_EXTENSION: &str = "scryfall-cache";
+
+#[derive(Debug, Clone)]
+pub struct CacheSettings {
+    pub directory: PathBuf,
+    pub time_to_live: Duration,
+    pub max_size_bytes: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            directory: std::env::temp_dir().join("scryfall_cache"),
+            time_to_live: Duration::from_secs(24 * 60 * 60),
+            max_size_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    stored_at: u64,
+    response: String,
+}
+
+// Stable across builds and platforms, unlike the standard library's hasher.
+fn fnv1a_hash(data: &str) -> u64 {
+    data.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn seconds_since_epoch() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+fn is_cache_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|extension| extension == CACHE_FILE_EXTENSION)
+}
+
+// Unreadable responses are treated as errors, so they are never cached.
+fn is_cacheable(response: &str) -> bool {
+    from_str::<Value>(response).is_ok_and(|value| !is_error_response(&value))
+}
+
+// Wraps another client and stores its responses on disk, keyed by the full request.
+// Collection requests are cached per card identifier, so overlapping deck lists share cached cards.
+pub struct CachingClient<Inner: RequestClient> {
+    inner: Inner,
+    settings: CacheSettings,
+    cache_size_bytes: AtomicU64,
+}
+
+impl<Inner: RequestClient> CachingClient<Inner> {
+    pub fn new(inner: Inner, settings: CacheSettings) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(&settings.directory)?;
+
+        let client = Self {
+            inner,
+            settings,
+            cache_size_bytes: AtomicU64::new(0),
+        };
+        client.prune()?;
+
+        Ok(client)
+    }
+
+    pub fn clear(directory: &Path) -> Result<(), Box<dyn Error>> {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(Box::new(error)),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if is_cache_file(&path) {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.settings.directory.join(format!("{:016x}.{CACHE_FILE_EXTENSION}", fnv1a_hash(key)))
+    }
+
+    fn read(&self, key: &str) -> Option<String> {
+        let entry: CacheEntry = from_str(&fs::read_to_string(self.entry_path(key)).ok()?).ok()?;
+
+        // Different keys can share a file name, the stored key tells them apart.
+        if entry.key != key || seconds_since_epoch().saturating_sub(entry.stored_at) > self.settings.time_to_live.as_secs() {
+            return None;
+        }
+
+        info!("Using cached response for {key}");
+        Some(entry.response)
+    }
+
+    fn write(&self, key: String, response: String) {
+        let path = self.entry_path(&key);
+        let entry = CacheEntry { key, stored_at: seconds_since_epoch(), response };
+
+        let result = to_string(&entry).map_err(Box::<dyn Error>::from).and_then(|contents| {
+            fs::write(&path, &contents)?;
+            Ok(contents.len() as u64)
+        });
+
+        // A failure to cache should never fail the request itself.
+        match result {
+            Ok(written_bytes) => {
+                let cache_size = self.cache_size_bytes.fetch_add(written_bytes, Ordering::Relaxed) + written_bytes;
+                if cache_size > self.settings.max_size_bytes && let Err(error) = self.prune() {
+                    warn!("Could not prune response cache: {error}");
+                }
+            },
+            Err(error) => warn!("Could not write response to cache: {error}"),
+        }
+    }
+
+    // Removes expired entries, then the oldest entries until the cache fits in its maximum size.
+    fn prune(&self) -> Result<(), Box<dyn Error>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&self.settings.directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !is_cache_file(&path) {
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified()?;
+            if modified.elapsed().unwrap_or_default() > self.settings.time_to_live {
+                fs::remove_file(path)?;
+                continue;
+            }
+
+            entries.push((modified, metadata.len(), path));
+        }
+
+        entries.sort_by_key(|(modified, _, _)| *modified);
+
+        let mut cache_size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (_, size, path) in entries {
+            if cache_size <= self.settings.max_size_bytes {
+                break;
+            }
+
+            fs::remove_file(path)?;
+            cache_size -= size;
+        }
+
+        self.cache_size_bytes.store(cache_size, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn post_collection(&self, url: String, identifiers: &[Value]) -> Result<String, Box<dyn Error>> {
+        let mut cached_entries: Vec<Option<CollectionEntry>> = identifiers.iter()
+            .map(|identifier| self.read(&collection_entry_key(&url, identifier)).and_then(|entry| from_str(&entry).ok()))
+            .collect();
+
+        let uncached_identifiers: Vec<Value> = identifiers.iter().zip(&cached_entries)
+            .filter(|(_, cached_entry)| cached_entry.is_none())
+            .map(|(identifier, _)| identifier.clone())
+            .collect();
+
+        if !uncached_identifiers.is_empty() {
+            let response = self.inner.post(url.clone(), &json!({ "identifiers": uncached_identifiers })).await?;
+            if !is_cacheable(&response) {
+                return Ok(response);
+            }
+
+            let mut fetched_entries = split_collection_response(&uncached_identifiers, &from_str(&response)?)?.into_iter();
+            for (identifier, cached_entry) in identifiers.iter().zip(cached_entries.iter_mut()).filter(|(_, cached_entry)| cached_entry.is_none()) {
+                let Some(fetched_entry) = fetched_entries.next() else {
+                    break;
+                };
+
+                self.write(collection_entry_key(&url, identifier), to_string(&fetched_entry)?);
+                *cached_entry = Some(fetched_entry);
+            }
+        }
+
+        Ok(join_collection_entries(cached_entries.into_iter().flatten()).to_string())
+    }
+}
+
+impl<Inner: RequestClient> RequestClient for CachingClient<Inner> {
+    fn build(settings: &ClientSettings) -> Result<Self, Box<dyn Error>> {
+        Self::new(Inner::build(settings)?, CacheSettings::default())
+    }
+
+    async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
+        let key = describe_request("GET", &url, &[], None);
+        if let Some(response) = self.read(&key) {
+            return Ok(response);
+        }
+
+        let response = self.inner.get(url).await?;
+        if is_cacheable(&response) {
+            self.write(key, response.clone());
+        }
+
+        Ok(response)
+    }
+
+    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        let key = describe_request("GET", &url, query_parameters, None);
+        if let Some(response) = self.read(&key) {
+            return Ok(response);
+        }
+
+        let response = self.inner.get_with_parameters(url, query_parameters).await?;
+        if is_cacheable(&response) {
+            self.write(key, response.clone());
+        }
+
+        Ok(response)
+    }
+
+    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
+        if let Some(identifiers) = collection_identifiers(&url, payload) {
+            return self.post_collection(url, identifiers).await;
+        }
+
+        let key = describe_request("POST", &url, &[], Some(payload));
+        if let Some(response) = self.read(&key) {
+            return Ok(response);
+        }
+
+        let response = self.inner.post(url, payload).await?;
+        if is_cacheable(&response) {
+            self.write(key, response.clone());
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use futures::executor::block_on;
+    use uuid::Uuid;
+    use super::*;
+    use crate::{api_interface::{api_classes::ApiObject, bulk_data_client::BulkDataClient, collection_card_identifier::CollectionCardIdentifier, ApiInterface}, test_cards::test_card};
+
+    // Records the collection requests that reach the underlying client.
+    struct CountingClient {
+        inner: BulkDataClient,
+        posted_payloads: RefCell<Vec<Value>>,
+    }
+
+    impl RequestClient for CountingClient {
+        fn build(settings: &ClientSettings) -> Result<Self, Box<dyn Error>> {
+            Ok(Self { inner: BulkDataClient::build(settings)?, posted_payloads: RefCell::new(Vec::new()) })
+        }
+
+        async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
+            self.inner.get(url).await
+        }
+
+        async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+            self.inner.get_with_parameters(url, query_parameters).await
+        }
+
+        async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
+            self.posted_payloads.borrow_mut().push(payload.clone());
+            self.inner.post(url, payload).await
+        }
+    }
+
+    #[test]
+    fn test_collection_cached_per_identifier() {
+        let directory = std::env::temp_dir().join(format!("scryfall_cache_test_{}", std::process::id()));
+        CachingClient::<CountingClient>::clear(&directory).expect("Test cache should be cleared");
+
+        let counting_client = CountingClient {
+            inner: BulkDataClient::from_cards(vec![test_card(1, "Opt", "XLN", "65"), test_card(2, "Lightning Bolt", "M10", "146")]),
+            posted_payloads: RefCell::new(Vec::new()),
+        };
+        let settings = CacheSettings { directory: directory.clone(), ..CacheSettings::default() };
+        let interface = ApiInterface::from_client(CachingClient::new(counting_client, settings).expect("Cache should initialise"));
+
+        let opt = CollectionCardIdentifier::Name { name: "Opt".to_owned() };
+        let bolt = CollectionCardIdentifier::Name { name: "Lightning Bolt".to_owned() };
+        let missing = CollectionCardIdentifier::Name { name: "Not A Card".to_owned() };
+
+        block_on(interface.get_cards_from_list(&[opt.clone(), missing.clone()])).expect("First collection request should succeed");
+        let ApiObject::List(list) = block_on(interface.get_cards_from_list(&[bolt.clone(), opt, missing.clone()])).expect("Second collection request should succeed") else {
+            panic!("Collection request should return a list");
+        };
+
+        // Only the identifier that was not requested before reaches the underlying client.
+        let posted_payloads = interface.http_client.inner.posted_payloads.borrow();
+        assert_eq!(posted_payloads.len(), 2);
+        assert_eq!(posted_payloads[1], json!({ "identifiers": [bolt] }));
+
+        let found: Vec<Uuid> = list.data.iter().map(|object| match object {
+            ApiObject::Card(card) => card.id,
+            other => panic!("Expected a card, found {other}"),
+        }).collect();
+        assert_eq!(found, vec![Uuid::from_u128(2), Uuid::from_u128(1)]);
+        assert_eq!(list.not_found, Some(vec![missing]));
+
+        CachingClient::<CountingClient>::clear(&directory).expect("Test cache should be cleared");
+    }
+
+    #[test]
+    fn test_only_cache_files_removed() {
+        let directory = std::env::temp_dir().join(format!("scryfall_cache_foreign_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).expect("Test directory should be created");
+        let deck_path = directory.join("deck.json");
+        fs::write(&deck_path, "{\"object\": \"deck\"}").expect("Deck file should be written");
+
+        // Every cache file is expired and over the maximum size, so pruning removes all of them.
+        let settings = CacheSettings { directory: directory.clone(), time_to_live: Duration::ZERO, max_size_bytes: 0 };
+        let client = CachingClient::new(BulkDataClient::from_cards(Vec::new()), settings).expect("Cache should initialise");
+        client.write("GET https://api.scryfall.com/cards/named?exact=Opt".to_owned(), "{}".to_owned());
+        client.prune().expect("Cache should be pruned");
+
+        assert!(deck_path.exists());
+        assert_eq!(fs::read_dir(&directory).expect("Test directory should be readable").count(), 1);
+
+        let settings = CacheSettings { directory: directory.clone(), ..CacheSettings::default() };
+        let client = CachingClient::new(BulkDataClient::from_cards(Vec::new()), settings).expect("Cache should initialise");
+        client.write("GET https://api.scryfall.com/cards/named?exact=Opt".to_owned(), "{}".to_owned());
+        assert_eq!(fs::read_dir(&directory).expect("Test directory should be readable").count(), 2);
+
+        CachingClient::<BulkDataClient>::clear(&directory).expect("Test cache should be cleared");
+        assert!(deck_path.exists());
+        assert_eq!(fs::read_dir(&directory).expect("Test directory should be readable").count(), 1);
+
+        fs::remove_dir_all(&directory).expect("Test directory should be removed");
+    }
+}
@@ -0,0 +1,40 @@
+use core::time::Duration;
+use alloc::string::String;
+
+use super::retry_policy::RetryPolicy;
+
+pub const DEFAULT_API_ENDPOINT: &str = "https://api.scryfall.com";
+
+// Settings passed to RequestClient::build, clients ignore the ones they cannot apply.
+#[derive(Debug, Clone)]
+pub struct ClientSettings {
+    // Defaults to the name and version of this crate.
+    pub user_agent: Option<String>,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    // URL of a proxy for every request, such as http://localhost:8080.
+    pub proxy: Option<String>,
+    // Scryfall asks for no more than 10 requests per second.
+    pub requests_per_second: u32,
+    pub retry_policy: RetryPolicy,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            requests_per_second: 10,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+impl ClientSettings {
+    // Never returns zero, so requests cannot be blocked forever by a misconfigured rate.
+    pub fn request_interval_ms(&self) -> u64 {
+        1000 / self.requests_per_second.clamp(1, 1000) as u64
+    }
+}
@@ -3,6 +3,8 @@ use alloc::{borrow::ToOwned, string::{String, ToString}};
 use uuid::Uuid;
 use serde::{ser::SerializeStruct, Serialize, Deserialize};
 
+// Untagged variants are tried in order and unknown fields are ignored,
+// so identifiers with more fields must come before those with a subset of them.
 #[derive(Deserialize, Debug, Clone, Eq)]
 #[serde(untagged)]
 pub enum CollectionCardIdentifier {
@@ -11,9 +13,9 @@ pub enum CollectionCardIdentifier {
     MultiverseId { multiverse_id: usize },
     OracleId { oracle_id: Uuid },
     IllustrationId { illustration_id: Uuid },
-    Name { name: String },
     NameSet { name: String, set: String },
     CollectorNumberSet { collector_number: String, set: String},
+    Name { name: String },
 }
 
 impl Hash for CollectionCardIdentifier {
@@ -0,0 +1,176 @@
+use core::{cell::RefCell, error::Error, fmt::Display};
+use alloc::{boxed::Box, string::{String, ToString}, vec::Vec};
+use log::warn;
+use serde_json::{from_str, from_value, to_string_pretty, Map, Value};
+
+use super::{client_settings::ClientSettings, request_keys::{collection_entry_key, collection_identifiers, describe_request, is_error_response, join_collection_entries, split_collection_response, CollectionEntry}, RequestClient};
+
+#[derive(Debug, Clone)]
+pub struct MissingFixtureError {
+    request: String,
+}
+
+impl Display for MissingFixtureError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "No recorded response for request {}, the fixtures need to be recorded again", self.request)
+    }
+}
+
+impl Error for MissingFixtureError {}
+
+// Fixtures map request descriptions to the responses received for them.
+// Collection requests are stored per identifier, as the identifiers in one request depend on hash map ordering.
+pub type Fixtures = Map<String, Value>;
+
+// Serves recorded responses back without any network access, failing on any request that was not recorded.
+// Responses can also be added one by one to script the API's behaviour in tests.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayClient {
+    fixtures: Fixtures,
+    requests: RefCell<Vec<String>>,
+}
+
+impl ReplayClient {
+    pub fn from_fixtures(fixtures_json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            fixtures: from_str(fixtures_json)?,
+            requests: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn with_response(mut self, request: String, response: Value) -> Self {
+        self.fixtures.insert(request, response);
+        self
+    }
+
+    pub fn with_collection_entry(mut self, url: &str, identifier: &Value, entry: CollectionEntry) -> Self {
+        match serde_json::to_value(entry) {
+            Ok(entry) => {
+                self.fixtures.insert(collection_entry_key(url, identifier), entry);
+            },
+            Err(error) => warn!("Could not serialise collection entry: {error}"),
+        }
+        self
+    }
+
+    // Descriptions of every request received so far, in the order they were made.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.borrow().clone()
+    }
+
+    fn replay(&self, request: String) -> Result<&Value, Box<dyn Error>> {
+        self.requests.borrow_mut().push(request.clone());
+
+        match self.fixtures.get(&request) {
+            Some(response) => Ok(response),
+            None => Err(Box::new(MissingFixtureError { request })),
+        }
+    }
+}
+
+impl RequestClient for ReplayClient {
+    fn build(_settings: &ClientSettings) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::default())
+    }
+
+    async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
+        Ok(self.replay(describe_request("GET", &url, &[], None))?.to_string())
+    }
+
+    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        Ok(self.replay(describe_request("GET", &url, query_parameters, None))?.to_string())
+    }
+
+    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
+        let Some(identifiers) = collection_identifiers(&url, payload) else {
+            return Ok(self.replay(describe_request("POST", &url, &[], Some(payload)))?.to_string());
+        };
+
+        let mut entries = Vec::new();
+        for identifier in identifiers {
+            let entry: CollectionEntry = from_value(self.replay(collection_entry_key(&url, identifier))?.clone())?;
+            entries.push(entry);
+        }
+
+        Ok(join_collection_entries(entries).to_string())
+    }
+}
+
+// Passes requests through to another client and keeps every response, to be saved as fixtures for a ReplayClient.
+pub struct RecordingClient<Inner: RequestClient> {
+    inner: Inner,
+    fixtures: RefCell<Fixtures>,
+}
+
+impl<Inner: RequestClient> RecordingClient<Inner> {
+    pub fn new(inner: Inner) -> Self {
+        Self {
+            inner,
+            fixtures: RefCell::new(Fixtures::new()),
+        }
+    }
+
+    pub fn fixtures_json(&self) -> Result<String, serde_json::Error> {
+        to_string_pretty(&*self.fixtures.borrow())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: &std::path::Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, self.fixtures_json()? + "\n")?;
+        Ok(())
+    }
+
+    fn record(&self, request: String, response: &str) {
+        match from_str(response) {
+            Ok(response) => {
+                self.fixtures.borrow_mut().insert(request, response);
+            },
+            Err(error) => warn!("Not recording response to {request} as it is not valid JSON: {error}"),
+        }
+    }
+}
+
+impl<Inner: RequestClient> RequestClient for RecordingClient<Inner> {
+    fn build(settings: &ClientSettings) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(Inner::build(settings)?))
+    }
+
+    async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
+        let request = describe_request("GET", &url, &[], None);
+        let response = self.inner.get(url).await?;
+
+        self.record(request, &response);
+        Ok(response)
+    }
+
+    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        let request = describe_request("GET", &url, query_parameters, None);
+        let response = self.inner.get_with_parameters(url, query_parameters).await?;
+
+        self.record(request, &response);
+        Ok(response)
+    }
+
+    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
+        let response = self.inner.post(url.clone(), payload).await?;
+
+        let Some(identifiers) = collection_identifiers(&url, payload) else {
+            self.record(describe_request("POST", &url, &[], Some(payload)), &response);
+            return Ok(response);
+        };
+
+        let parsed_response: Value = from_str(&response)?;
+        if is_error_response(&parsed_response) {
+            warn!("Not recording error response to collection request: {response}");
+            return Ok(response);
+        }
+
+        let mut fixtures = self.fixtures.borrow_mut();
+        for (identifier, entry) in identifiers.iter().zip(split_collection_response(identifiers, &parsed_response)?) {
+            fixtures.insert(collection_entry_key(&url, identifier), serde_json::to_value(entry)?);
+        }
+
+        Ok(response)
+    }
+}
+
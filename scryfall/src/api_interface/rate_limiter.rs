@@ -0,0 +1,90 @@
+use core::{future::Future, sync::atomic::{AtomicU64, Ordering}};
+
+// Source of time for the rate limiter, so it can run on browser timers and be tested without waiting.
+pub trait Clock {
+    fn now_ms(&self) -> u64;
+    fn sleep(&self, milliseconds: u64) -> impl Future<Output = ()>;
+}
+
+// Spaces requests out evenly by handing each one the next free time slot.
+// The state is kept in atomics so a limiter can be a static shared by every client.
+pub struct RateLimiter {
+    interval_ms: AtomicU64,
+    next_slot_ms: AtomicU64,
+}
+
+impl RateLimiter {
+    pub const fn new(interval_ms: u64) -> Self {
+        Self {
+            interval_ms: AtomicU64::new(interval_ms),
+            next_slot_ms: AtomicU64::new(0),
+        }
+    }
+
+    // Applies to every client sharing the limiter, starting from the next request.
+    pub fn set_interval_ms(&self, interval_ms: u64) {
+        self.interval_ms.store(interval_ms, Ordering::Relaxed);
+    }
+
+    pub async fn until_ready(&self, clock: &impl Clock) {
+        let now = clock.now_ms();
+        let interval_ms = self.interval_ms.load(Ordering::Relaxed);
+
+        // The slot is reserved before waiting, so requests made while others wait queue up behind them.
+        let mut next_slot = self.next_slot_ms.load(Ordering::Relaxed);
+        let slot = loop {
+            let slot = next_slot.max(now);
+            match self.next_slot_ms.compare_exchange_weak(next_slot, slot + interval_ms, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break slot,
+                Err(current_next_slot) => next_slot = current_next_slot,
+            }
+        };
+
+        if slot > now {
+            clock.sleep(slot - now).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::{Cell, RefCell};
+    use alloc::{vec, vec::Vec};
+    use futures::{executor::block_on, future::join_all};
+    use super::*;
+
+    struct FakeClock {
+        now_ms: Cell<u64>,
+        sleeps: RefCell<Vec<u64>>,
+    }
+
+    impl Clock for FakeClock {
+        fn now_ms(&self) -> u64 {
+            self.now_ms.get()
+        }
+
+        async fn sleep(&self, milliseconds: u64) {
+            self.sleeps.borrow_mut().push(milliseconds);
+        }
+    }
+
+    #[test]
+    fn test_requests_spaced_out() {
+        let rate_limiter = RateLimiter::new(100);
+        let clock = FakeClock { now_ms: Cell::new(1000), sleeps: RefCell::new(Vec::new()) };
+
+        // Requests sent at the same time wait for consecutive slots.
+        block_on(join_all((0..3).map(|_| rate_limiter.until_ready(&clock))));
+        assert_eq!(*clock.sleeps.borrow(), vec![100, 200]);
+
+        // Later requests only wait for the remainder of the interval.
+        clock.now_ms.set(1250);
+        block_on(rate_limiter.until_ready(&clock));
+        assert_eq!(*clock.sleeps.borrow(), vec![100, 200, 50]);
+
+        // Once the limiter has caught up, requests go through immediately.
+        clock.now_ms.set(5000);
+        block_on(rate_limiter.until_ready(&clock));
+        assert_eq!(clock.sleeps.borrow().len(), 3);
+    }
+}
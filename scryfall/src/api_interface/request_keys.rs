@@ -0,0 +1,84 @@
+use alloc::{format, string::String, vec::Vec};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json, Value};
+
+use super::{collection_card_identifier::CollectionCardIdentifier, CARD_COLLECTION_METHOD};
+
+// Describes a request in a readable way that identifies it completely, for error messages, cache keys and fixtures.
+pub fn describe_request(method: &str, url: &str, query_parameters: &[(&str, &str)], payload: Option<&Value>) -> String {
+    let mut description = format!("{method} {url}");
+
+    for (index, (parameter_name, parameter_value)) in query_parameters.iter().enumerate() {
+        let separator = if index == 0 { '?' } else { '&' };
+        description += &format!("{separator}{parameter_name}={parameter_value}");
+    }
+    if let Some(payload) = payload {
+        description += &format!(" {payload}");
+    }
+
+    description
+}
+
+// Returns the identifiers of a collection request, which are stored per identifier rather than per request.
+// The identifiers in one request depend on how a deck list was split up, so whole requests rarely repeat.
+pub fn collection_identifiers<'a>(url: &str, payload: &'a Value) -> Option<&'a Vec<Value>> {
+    if !url.trim_end_matches('/').ends_with(CARD_COLLECTION_METHOD) {
+        return None;
+    }
+
+    payload.get("identifiers").and_then(Value::as_array)
+}
+
+pub fn collection_entry_key(url: &str, identifier: &Value) -> String {
+    describe_request("POST", url, &[], Some(identifier))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionEntry {
+    Card(Value),
+    NotFound(Value),
+}
+
+pub fn is_error_response(response: &Value) -> bool {
+    response.get("object").and_then(Value::as_str) == Some("error")
+}
+
+// Matches the cards in a collection response to the identifiers that were requested.
+// Found cards are returned in the order they were requested, skipping those that were not found.
+pub fn split_collection_response(identifiers: &[Value], response: &Value) -> Result<Vec<CollectionEntry>, serde_json::Error> {
+    let not_found: Vec<CollectionCardIdentifier> = response.get("not_found").cloned().map(from_value).transpose()?.unwrap_or_default();
+    let mut found_cards = response.get("data").and_then(Value::as_array).cloned().unwrap_or_default().into_iter();
+
+    let mut entries = Vec::new();
+    for identifier in identifiers {
+        let is_not_found = from_value::<CollectionCardIdentifier>(identifier.clone())
+            .is_ok_and(|parsed_identifier| not_found.contains(&parsed_identifier));
+
+        if is_not_found {
+            entries.push(CollectionEntry::NotFound(identifier.clone()));
+        } else if let Some(card) = found_cards.next() {
+            entries.push(CollectionEntry::Card(card));
+        } else {
+            warn!("Collection response has fewer cards than expected");
+            break;
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn join_collection_entries(entries: impl IntoIterator<Item = CollectionEntry>) -> Value {
+    let mut data = Vec::new();
+    let mut not_found = Vec::new();
+
+    for entry in entries {
+        match entry {
+            CollectionEntry::Card(card) => data.push(card),
+            CollectionEntry::NotFound(identifier) => not_found.push(identifier),
+        }
+    }
+
+    json!({ "object": "list", "data": data, "not_found": not_found })
+}
@@ -1,10 +1,12 @@
-use std::error::Error;
-use reqwest::{Client, header::ACCEPT};
+use std::{error::Error, hash::{BuildHasher, RandomState}, num::NonZeroU32, time::Duration};
+use futures_timer::Delay;
+use log::warn;
+use reqwest::{Client, Proxy, RequestBuilder, header::{ACCEPT, RETRY_AFTER}};
 use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use nonzero_ext::nonzero;
 use serde_json::Value;
 
-use super::RequestClient;
+use super::{client_settings::ClientSettings, request_keys::describe_request, retry_policy::{parse_retry_after_ms, RetriesExhaustedError, RetryPolicy}, RequestClient};
 
 static APP_USER_AGENT: &str = concat!(
     env!("CARGO_PKG_NAME"),
@@ -12,59 +14,95 @@ static APP_USER_AGENT: &str = concat!(
     env!("CARGO_PKG_VERSION"),
 );
 
+// Also used for downloading card images, so they go through the same proxy with the same user agent and timeouts.
+pub fn build_http_client(settings: &ClientSettings) -> Result<Client, Box<dyn Error>> {
+    let mut builder = Client::builder();
+    builder = builder.user_agent(settings.user_agent.as_deref().unwrap_or(APP_USER_AGENT));
+
+    if let Some(timeout) = settings.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(connect_timeout) = settings.connect_timeout {
+        builder = builder.connect_timeout(connect_timeout);
+    }
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    Ok(builder.build()?)
+}
+
 pub struct ReqwestWrapper {
     client: Client,
     rate_limiter: DefaultDirectRateLimiter,
+    retry_policy: RetryPolicy,
 }
 
-impl RequestClient for ReqwestWrapper {
-    fn build() -> Result<ReqwestWrapper, Box<(dyn Error)>> {
-        let mut builder = Client::builder();
-        builder = builder.user_agent(APP_USER_AGENT);
+// Every RandomState is seeded differently, which is enough randomness to spread out retries.
+fn jitter() -> f64 {
+    RandomState::new().hash_one(()) as f64 / u64::MAX as f64
+}
 
-        Ok(Self {
-            client: builder.build()?,
-            rate_limiter: RateLimiter::direct(Quota::per_second(nonzero!(10_u32))),
-        })
-    }
+impl ReqwestWrapper {
+    async fn send_with_retries(&self, request_description: String, build_request: impl Fn() -> RequestBuilder) -> Result<String, Box<dyn Error>> {
+        let mut retry = 0;
 
-    async fn get(&self, url: String) -> Result<String, Box<(dyn Error)>> {
-        self.rate_limiter.until_ready().await;
+        loop {
+            self.rate_limiter.until_ready().await;
 
-        let response = self.client.get(url)
-            .header(ACCEPT, "application/json")
-            .send().await?
-            .text().await?;
+            let response = build_request()
+                .header(ACCEPT, "application/json")
+                .send().await?;
 
-        Ok(response)
-    }
+            let status = response.status().as_u16();
+            if !RetryPolicy::is_retryable(status) {
+                return Ok(response.text().await?);
+            }
 
-    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<(dyn Error)>> {
-        self.rate_limiter.until_ready().await;
+            if retry >= self.retry_policy.max_retries {
+                return Err(Box::new(RetriesExhaustedError::new(request_description, status, retry + 1)));
+            }
 
-        let mut request = self.client.get(url);
+            let retry_after_ms = response.headers().get(RETRY_AFTER)
+                .and_then(|header_value| header_value.to_str().ok())
+                .and_then(parse_retry_after_ms);
+            let delay_ms = self.retry_policy.delay_ms(retry, retry_after_ms, jitter());
 
-        if !query_parameters.is_empty() {
-            request = request.query(query_parameters);
+            warn!("Request {request_description} failed with HTTP status {status}, retrying in {delay_ms}ms");
+            Delay::new(Duration::from_millis(delay_ms)).await;
+            retry += 1;
         }
+    }
+}
 
-        let response = request
-            .header(ACCEPT, "application/json")
-            .send().await?
-            .text().await?;
+impl RequestClient for ReqwestWrapper {
+    fn build(settings: &ClientSettings) -> Result<ReqwestWrapper, Box<dyn Error>> {
+        let requests_per_second = NonZeroU32::new(settings.requests_per_second).unwrap_or(nonzero!(1_u32));
 
-        Ok(response)
+        Ok(Self {
+            client: build_http_client(settings)?,
+            rate_limiter: RateLimiter::direct(Quota::per_second(requests_per_second)),
+            retry_policy: settings.retry_policy,
+        })
     }
 
-    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<(dyn Error)>> {
-        self.rate_limiter.until_ready().await;
+    async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
+        self.send_with_retries(describe_request("GET", &url, &[], None), || self.client.get(&url)).await
+    }
+
+    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        self.send_with_retries(describe_request("GET", &url, query_parameters, None), || {
+            let mut request = self.client.get(&url);
 
-        let response = self.client.post(url)
-            .json(payload)
-            .header(ACCEPT, "application/json")
-            .send().await?
-            .text().await?;
+            if !query_parameters.is_empty() {
+                request = request.query(query_parameters);
+            }
 
-        Ok(response)
+            request
+        }).await
     }
-}
\ No newline at end of file
+
+    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
+        self.send_with_retries(describe_request("POST", &url, &[], Some(payload)), || self.client.post(&url).json(payload)).await
+    }
+}
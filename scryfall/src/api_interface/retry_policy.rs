@@ -0,0 +1,89 @@
+use core::{error::Error, fmt::Display};
+use alloc::string::String;
+
+#[derive(Debug, Clone)]
+pub struct RetriesExhaustedError {
+    request: String,
+    status: u16,
+    attempts: u32,
+}
+
+impl RetriesExhaustedError {
+    pub fn new(request: String, status: u16, attempts: u32) -> Self {
+        Self {
+            request,
+            status,
+            attempts,
+        }
+    }
+}
+
+impl Display for RetriesExhaustedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Request {} still failed with HTTP status {} after {} attempts", self.request, self.status, self.attempts)
+    }
+}
+
+impl Error for RetriesExhaustedError {}
+
+// Parses a Retry-After header given in seconds, HTTP dates are left to the regular backoff.
+pub fn parse_retry_after_ms(header_value: &str) -> Option<u64> {
+    header_value.trim().parse::<u64>().ok().map(|seconds| seconds.saturating_mul(1000))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Scryfall answers 429 when requests are sent too quickly, server errors are usually temporary too.
+    pub fn is_retryable(status: u16) -> bool {
+        status == 429 || (500..600).contains(&status)
+    }
+
+    // Doubles the delay with each retry, with half of it randomised so concurrent clients spread out.
+    // The server's Retry-After takes precedence over the backoff, up to the same maximum. The jitter must be between 0 and 1.
+    pub fn delay_ms(&self, retry: u32, retry_after_ms: Option<u64>, jitter: f64) -> u64 {
+        if let Some(retry_after_ms) = retry_after_ms {
+            return retry_after_ms.min(self.max_delay_ms);
+        }
+
+        let backoff = self.base_delay_ms.saturating_mul(1_u64.checked_shl(retry).unwrap_or(u64::MAX)).min(self.max_delay_ms);
+        backoff / 2 + (backoff as f64 / 2.0 * jitter.clamp(0.0, 1.0)) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays() {
+        let policy = RetryPolicy::default();
+
+        assert!(RetryPolicy::is_retryable(429));
+        assert!(RetryPolicy::is_retryable(503));
+        assert!(!RetryPolicy::is_retryable(404));
+
+        assert_eq!(policy.delay_ms(0, None, 0.0), 250);
+        assert_eq!(policy.delay_ms(0, None, 1.0), 500);
+        assert_eq!(policy.delay_ms(2, None, 0.5), 1500);
+        assert_eq!(policy.delay_ms(100, None, 1.0), policy.max_delay_ms);
+        assert_eq!(policy.delay_ms(0, parse_retry_after_ms(" 2 "), 0.5), 2000);
+        assert_eq!(policy.delay_ms(0, parse_retry_after_ms("86400"), 0.5), policy.max_delay_ms);
+        assert_eq!(parse_retry_after_ms("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
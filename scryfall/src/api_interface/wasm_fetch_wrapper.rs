@@ -1,11 +1,12 @@
 use core::{error::Error, fmt::Display};
-use alloc::{string::{String, ToString}, boxed::Box, borrow::ToOwned};
+use alloc::{string::{String, ToString}, boxed::Box};
+use log::warn;
 use serde_json::Value;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{console::error_1, js_sys::JsString, window, Request, RequestInit, RequestMode, Response, Url, UrlSearchParams, Window};
+use web_sys::{console::error_1, AbortSignal, js_sys::{Date, Math::random, JsString, Promise}, window, Request, RequestInit, RequestMode, Response, Url, UrlSearchParams, Window};
 
-use super::RequestClient;
+use super::{client_settings::ClientSettings, rate_limiter::{Clock, RateLimiter}, request_keys::describe_request, retry_policy::{parse_retry_after_ms, RetriesExhaustedError, RetryPolicy}, RequestClient};
 
 #[derive(Debug, Clone)]
 pub struct JsErrorWrapper {
@@ -54,99 +55,144 @@ impl Display for NotStringError {
 
 impl Error for NotStringError {}
 
+// Scryfall asks for no more than 10 requests per second, shared between every interface on the page.
+static SHARED_RATE_LIMITER: RateLimiter = RateLimiter::new(100);
+
+pub struct BrowserClock {
+    window: Window,
+}
+
+impl Clock for BrowserClock {
+    fn now_ms(&self) -> u64 {
+        Date::now() as u64
+    }
+
+    async fn sleep(&self, milliseconds: u64) {
+        let mut timeout_result = Ok(0);
+        let promise = Promise::new(&mut |resolve, _reject| {
+            timeout_result = self.window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, milliseconds.min(i32::MAX as u64) as i32);
+        });
+
+        // Without a timer the request goes ahead straight away rather than never being sent.
+        if let Err(js_error) = timeout_result {
+            error_1(&js_error);
+            return;
+        }
+
+        if let Err(js_error) = JsFuture::from(promise).await {
+            error_1(&js_error);
+        }
+    }
+}
+
 pub struct WasmFetchWrapper {
     window: Window,
+    clock: BrowserClock,
+    timeout_ms: Option<u32>,
+    retry_policy: RetryPolicy,
 }
 
 impl WasmFetchWrapper {
-    async fn _get(&self, url: String, opts: RequestInit) -> Result<JsValue, JsValue> {
-        let request = Request::new_with_str_and_init(url.as_str(), &opts)?;
-        request.headers().set("Accept", "application/json")?;
+    async fn _fetch(&self, url: &str, opts: &RequestInit, has_body: bool) -> Result<Response, JsValue> {
+        // A signal only times out once, so each attempt needs a new one.
+        if let Some(timeout_ms) = self.timeout_ms {
+            opts.set_signal(Some(&AbortSignal::timeout_with_u32(timeout_ms)));
+        }
 
-        let response: Response = JsFuture::from(self.window.fetch_with_request(&request)).await?.dyn_into()?;
-        let json = JsFuture::from(response.text()?).await?;
+        let request = Request::new_with_str_and_init(url, opts)?;
+        request.headers().set("Accept", "application/json")?;
+        if has_body {
+            request.headers().set("Content-Type", "application/json")?;
+        }
 
-        Ok(json)
+        JsFuture::from(self.window.fetch_with_request(&request)).await?.dyn_into()
     }
 
-    async fn _post(&self, url: String, opts: RequestInit) -> Result<JsValue, JsValue> {
-        let request = Request::new_with_str_and_init(url.as_str(), &opts)?;
-        request.headers().set("Accept", "application/json")?;
-        request.headers().set("Content-Type", "application/json")?;
+    async fn send_with_retries(&self, request_description: String, url: &str, opts: &RequestInit, has_body: bool) -> Result<String, Box<dyn Error>> {
+        let mut retry = 0;
+
+        loop {
+            SHARED_RATE_LIMITER.until_ready(&self.clock).await;
+
+            let response = self._fetch(url, opts, has_body).await.map_err(JsErrorWrapper::from)?;
+
+            let status = response.status();
+            if !RetryPolicy::is_retryable(status) {
+                let text_promise = response.text().map_err(JsErrorWrapper::from)?;
+                let json_value = JsFuture::from(text_promise).await.map_err(JsErrorWrapper::from)?;
+
+                let Some(json) = json_value.as_string() else {
+                    return Err(NotStringError { not_string_value: json_value }.into());
+                };
 
-        let response: Response = JsFuture::from(self.window.fetch_with_request(&request)).await?.dyn_into()?;
-        let json = JsFuture::from(response.text()?).await?;
+                return Ok(json);
+            }
 
-        Ok(json)
+            if retry >= self.retry_policy.max_retries {
+                return Err(Box::new(RetriesExhaustedError::new(request_description, status, retry + 1)));
+            }
+
+            // Browsers hide Retry-After from cross-origin responses unless the server exposes it, the backoff covers that case.
+            let retry_after_ms = response.headers().get("Retry-After").ok().flatten().as_deref().and_then(parse_retry_after_ms);
+            let delay_ms = self.retry_policy.delay_ms(retry, retry_after_ms, random());
+
+            warn!("Request {request_description} failed with HTTP status {status}, retrying in {delay_ms}ms");
+            self.clock.sleep(delay_ms).await;
+            retry += 1;
+        }
     }
 }
 
 impl RequestClient for WasmFetchWrapper {
-    fn build() -> Result<WasmFetchWrapper, Box<(dyn Error)>> {
+    fn build(settings: &ClientSettings) -> Result<WasmFetchWrapper, Box<dyn Error>> {
         let Some(window) = window() else {
             return Err(NoWindowError {}.into());
         };
 
+        // Browsers choose the user agent, proxy and connection handling themselves.
+        if settings.user_agent.is_some() || settings.proxy.is_some() || settings.connect_timeout.is_some() {
+            warn!("User agent, proxy and connect timeout settings have no effect in the browser");
+        }
+
+        SHARED_RATE_LIMITER.set_interval_ms(settings.request_interval_ms());
+
         Ok(Self {
-            window
+            clock: BrowserClock { window: window.clone() },
+            window,
+            timeout_ms: settings.timeout.map(|timeout| timeout.as_millis().min(u32::MAX as u128) as u32),
+            retry_policy: settings.retry_policy,
         })
     }
 
-    async fn get(&self, url: String) -> Result<String, Box<(dyn Error)>> {
+    async fn get(&self, url: String) -> Result<String, Box<dyn Error>> {
         let opts = RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(RequestMode::Cors);
 
-        let json_value = match self._get(url, opts).await {
-            Ok(json) => json.dyn_into::<JsString>().map_err(|not_string_value: JsValue| NotStringError { not_string_value })?,
-            Err(js_error) => return Err(Box::<JsErrorWrapper>::new(js_error.into())),
-        };
-
-        let Some(json) = json_value.as_string() else {
-            return Err(NotStringError { not_string_value: json_value.into() }.into());
-        };
-
-        Ok(json.as_str().to_owned())
+        self.send_with_retries(describe_request("GET", &url, &[], None), &url, &opts, false).await
     }
 
-    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<(dyn Error)>> {
+    async fn get_with_parameters(&self, url: String, query_parameters: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
         let opts = RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(RequestMode::Cors);
 
+        let request_description = describe_request("GET", &url, query_parameters, None);
         let url_with_query = match set_query_parameters(url, query_parameters) {
             Ok(url) => url,
             Err(js_error) => return Err(Box::<JsErrorWrapper>::new(js_error.into())),
         };
 
-        let json_value = match self._get(url_with_query, opts).await {
-            Ok(json) => json.dyn_into::<JsString>().map_err(|not_string_value: JsValue| NotStringError { not_string_value })?,
-            Err(js_error) => return Err(Box::<JsErrorWrapper>::new(js_error.into())),
-        };
-
-        let Some(json) = json_value.as_string() else {
-            return Err(NotStringError { not_string_value: json_value.into() }.into());
-        };
-
-        Ok(json.as_str().to_owned())
+        self.send_with_retries(request_description, &url_with_query, &opts, false).await
     }
 
-    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<(dyn Error)>> {
+    async fn post(&self, url: String, payload: &Value) -> Result<String, Box<dyn Error>> {
         let opts = RequestInit::new();
         opts.set_method("POST");
         opts.set_mode(RequestMode::Cors);
         opts.set_body(&JsValue::from(payload.to_string()));
 
-        let json_value = match self._post(url, opts).await {
-            Ok(json) => json.dyn_into::<JsString>().map_err(|not_string_value: JsValue| NotStringError { not_string_value })?,
-            Err(js_error) => return Err(Box::<JsErrorWrapper>::new(js_error.into())),
-        };
-
-        let Some(json) = json_value.as_string() else {
-            return Err(NotStringError { not_string_value: json_value.into() }.into());
-        };
-
-        Ok(json.as_str().to_owned())
+        self.send_with_retries(describe_request("POST", &url, &[], Some(payload)), &url, &opts, true).await
     }
 }
 
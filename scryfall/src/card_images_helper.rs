@@ -11,6 +11,9 @@ use crate::api_interface::api_classes::{Card, ImageUris};
 #[cfg(feature = "std")]
 use crate::api_interface::api_classes::Deck;
 
+// The standard Magic card back, as served by Scryfall.
+pub const DEFAULT_CARD_BACK_URL: &str = "https://backs.scryfall.io/large/0/a/0aeebaf5-8c7d-4636-9e82-8c27447861f7.jpg";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ImageUriType {
     Small,
@@ -32,6 +35,17 @@ fn extract_image(images: &ImageUris, image_type: ImageUriType) -> String {
     }
 }
 
+// Borderless cards have no border to extend, so black is used as it blends best with most card art.
+pub fn border_rgb(card: &Card) -> [u8; 3] {
+    match card.border_color.as_str() {
+        "white" => [255, 255, 255],
+        "silver" => [192, 192, 192],
+        "gold" => [212, 175, 55],
+        "yellow" => [245, 227, 59],
+        _ => [0, 0, 0],
+    }
+}
+
 pub fn extract_images(cards: Vec<Card>, exclude_basic_lands: bool, image_type: ImageUriType) -> Vec<(Card, Vec<String>)> {
     let filtered_cards = if exclude_basic_lands {
         cards.iter().filter(|card| card.type_line.as_ref().is_none_or(|type_line| !type_line.starts_with("Basic Land"))).cloned().collect()
@@ -95,7 +109,7 @@ pub fn extract_images_from_json_file(file: &File, exclude_basic_lands: bool) ->
             }
 
             if card.found {
-                error!("Could not find image for card {}", &card.raw_text);
+                error!("Could not find image for card {}", card.raw_text);
             }
         }
     }
@@ -0,0 +1,97 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+// A row of a CSV file, quoted fields can span several lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CsvRecord<'a> {
+    pub line_number: usize,
+    // The row as written in the file, without the line break.
+    pub source: &'a str,
+    pub fields: Vec<Cow<'a, str>>,
+}
+
+// Guesses the delimiter from the header row, as spreadsheets in some locales use semicolons.
+pub(crate) fn detect_delimiter(csv_data: &str) -> char {
+    let header = csv_data.lines().next().unwrap_or_default();
+
+    [',', ';', '\t'].into_iter()
+        .max_by_key(|delimiter| header.matches(*delimiter).count())
+        .filter(|delimiter| header.contains(*delimiter))
+        .unwrap_or(',')
+}
+
+// Splits CSV data into rows as described in RFC 4180, skipping empty lines.
+pub(crate) fn records(csv_data: &str, delimiter: char) -> impl Iterator<Item = CsvRecord<'_>> {
+    let mut rest = csv_data;
+    let mut line_number = 1;
+
+    core::iter::from_fn(move || loop {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let record_line_number = line_number;
+        let mut fields = Vec::new();
+        let mut field_start = 0;
+        let mut quoted_field: Option<String> = None;
+        let mut in_quotes = false;
+        let mut record_end = rest.len();
+        let mut characters = rest.char_indices().peekable();
+
+        while let Some((index, character)) = characters.next() {
+            match character {
+                '"' if in_quotes && characters.peek().is_some_and(|(_, next)| *next == '"') => {
+                    characters.next();
+                    quoted_field.get_or_insert_with(String::new).push('"');
+                },
+                '"' if in_quotes => in_quotes = false,
+                '"' if rest[field_start..index].trim().is_empty() => {
+                    in_quotes = true;
+                    quoted_field = Some(String::new());
+                },
+                '\n' if !in_quotes => {
+                    record_end = index;
+                    break;
+                },
+                _ if in_quotes => {
+                    if character == '\n' {
+                        line_number += 1;
+                    }
+                    quoted_field.get_or_insert_with(String::new).push(character);
+                },
+                _ if character == delimiter => {
+                    fields.push(take_field(rest, field_start, index, quoted_field.take()));
+                    field_start = index + character.len_utf8();
+                },
+                _ => {},
+            }
+        }
+
+        let source = rest[..record_end].strip_suffix('\r').unwrap_or(&rest[..record_end]);
+        fields.push(take_field(rest, field_start, field_start.max(source.len()), quoted_field.take()));
+        rest = rest.get(record_end + 1..).unwrap_or_default();
+        line_number += 1;
+
+        if fields.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+
+        return Some(CsvRecord { line_number: record_line_number, source, fields });
+    })
+}
+
+// Unquoted fields are borrowed, quoted ones have their quotes removed and doubled quotes unescaped.
+fn take_field(record: &str, field_start: usize, field_end: usize, quoted_field: Option<String>) -> Cow<'_, str> {
+    match quoted_field {
+        Some(quoted_field) => Cow::Owned(quoted_field),
+        None => Cow::Borrowed(record[field_start..field_end].trim()),
+    }
+}
+
+// Quotes fields that contain the delimiter, quotes or line breaks, doubling any quotes inside.
+pub fn escape(field: &str, delimiter: char) -> Cow<'_, str> {
+    if !field.contains([delimiter, '"', '\n', '\r']) {
+        return Cow::Borrowed(field);
+    }
+
+    Cow::Owned(alloc::format!("\"{}\"", field.replace('"', "\"\"")))
+}
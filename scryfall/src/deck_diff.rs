@@ -25,9 +25,41 @@ pub fn deck_diff(old_deck: Vec<ResolvedCard>, new_deck: Vec<ResolvedCard>) -> De
         cards
     }));
 
-    let unchanged: Vec<Card> = new_cards_set.union(&old_cards_set).map(|card| card.card.clone()).collect();
+    let unchanged: Vec<Card> = new_cards_set.intersection(&old_cards_set).map(|card| card.card.clone()).collect();
     let added: Vec<Card> = new_cards_set.difference(&old_cards_set).map(|card| card.card.clone()).collect();
     let removed: Vec<Card> = old_cards_set.difference(&new_cards_set).map(|card| card.card.clone()).collect();
 
     DeckDiff { unchanged, added, removed }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, vec, vec::Vec};
+    use futures::executor::block_on;
+    use super::*;
+    use crate::{api_interface::collection_card_identifier::CollectionCardIdentifier, deck_list::{DeckList, DeckListEntry}, fetch_card_data::fetch_deck::FetchDeck, test_cards::fixture_interface};
+
+    fn sorted_names(cards: &[Card]) -> Vec<&str> {
+        let mut names: Vec<&str> = cards.iter().map(|card| card.name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_deck_diff() {
+        let interface = fixture_interface();
+        let name = |name: &str| CollectionCardIdentifier::Name { name: name.to_owned() };
+
+        let old_deck_list = DeckList::from_iter([DeckListEntry::new(name("Opt"), 4), DeckListEntry::new(name("Lightning Bolt"), 2), DeckListEntry::new(name("Young Pyromancer"), 1)]);
+        let new_deck_list = DeckList::from_iter([DeckListEntry::new(name("Opt"), 4), DeckListEntry::new(name("Lightning Bolt"), 3), DeckListEntry::new(name("Jace Belern"), 1)]);
+
+        let old_deck = block_on(interface.fetch_deck(&old_deck_list, false)).expect("Old deck should resolve from fixtures");
+        let new_deck = block_on(interface.fetch_deck(&new_deck_list, false)).expect("New deck should resolve from fixtures");
+
+        let difference = deck_diff(old_deck, new_deck);
+
+        assert_eq!(sorted_names(&difference.added), vec!["Jace Beleren", "Lightning Bolt"]);
+        assert_eq!(sorted_names(&difference.removed), vec!["Young Pyromancer"]);
+        assert_eq!(sorted_names(&difference.unchanged), vec!["Lightning Bolt", "Lightning Bolt", "Opt", "Opt", "Opt", "Opt"]);
+    }
+}
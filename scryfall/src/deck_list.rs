@@ -0,0 +1,243 @@
+use core::fmt::Display;
+use alloc::{string::String, vec::Vec};
+use hashbrown::HashMap;
+
+use crate::api_interface::collection_card_identifier::CollectionCardIdentifier;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum DeckSection {
+    Commander,
+    Companion,
+    #[default]
+    Main,
+    Sideboard,
+    Maybeboard,
+}
+
+impl DeckSection {
+    // Recognises section headers such as "Sideboard", "// Commander" or "Maybeboard (3)", and Scryfall's section names.
+    pub fn from_header(header: &str) -> Option<Self> {
+        let header = header.trim().trim_start_matches('/').trim().trim_end_matches(':');
+        let header = match header.rfind('(') {
+            Some(count_start) if header.ends_with(')') => header[..count_start].trim(),
+            _ => header,
+        };
+
+        match header.to_ascii_lowercase().as_str() {
+            "commander" | "commanders" => Some(Self::Commander),
+            "companion" | "companions" => Some(Self::Companion),
+            "deck" | "main" | "maindeck" | "main deck" | "mainboard" | "nonlands" | "lands" => Some(Self::Main),
+            "sideboard" | "side" | "outside" => Some(Self::Sideboard),
+            "maybeboard" | "maybe" | "considering" => Some(Self::Maybeboard),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DeckSection {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Commander => write!(f, "Commander"),
+            Self::Companion => write!(f, "Companion"),
+            Self::Main => write!(f, "Deck"),
+            Self::Sideboard => write!(f, "Sideboard"),
+            Self::Maybeboard => write!(f, "Maybeboard"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CardFinish {
+    #[default]
+    Nonfoil,
+    Foil,
+    Etched,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Annotation {
+    // Text between angle brackets, such as <proxy> or <cost={WU}{U}>.
+    Tag(String),
+    // Text after a #, up to the end of the line.
+    Comment(String),
+    // Deck building categories, such as Ramp or Removal, without any flags.
+    Category(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeckListEntry {
+    pub section: DeckSection,
+    pub count: usize,
+    pub identifier: CollectionCardIdentifier,
+    pub finish: CardFinish,
+    pub annotations: Vec<Annotation>,
+}
+
+impl DeckListEntry {
+    pub fn new(identifier: CollectionCardIdentifier, count: usize) -> Self {
+        Self {
+            section: DeckSection::default(),
+            count,
+            identifier,
+            finish: CardFinish::default(),
+            annotations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticReason {
+    // Neither a card line nor a section header, such as a card name without a count.
+    UnrecognisedLine,
+    // The count does not fit in a number, the line is skipped.
+    InvalidCount,
+    // The line is kept but adds no cards.
+    ZeroCount,
+    // The card name contains characters that mark sets, finishes, categories or tags, so part of the line was probably misread.
+    SuspiciousName,
+}
+
+impl DiagnosticReason {
+    pub fn skips_line(&self) -> bool {
+        matches!(self, Self::UnrecognisedLine | Self::InvalidCount)
+    }
+}
+
+impl Display for DiagnosticReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnrecognisedLine => write!(f, "not a card line or section header, the line was skipped"),
+            Self::InvalidCount => write!(f, "the count is not a valid number, the line was skipped"),
+            Self::ZeroCount => write!(f, "the count is zero, no cards were added"),
+            Self::SuspiciousName => write!(f, "the card name contains *, [, ], < or >, part of the line may have been misread"),
+        }
+    }
+}
+
+// A skipped or suspicious line of a deck list, line numbers start at 1 and count empty lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDiagnostic {
+    pub line_number: usize,
+    pub line: String,
+    pub reason: DiagnosticReason,
+}
+
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Line {} \"{}\": {}", self.line_number, self.line, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeDuplicates {
+    // Combines every copy of a card into the first entry for it, regardless of section.
+    #[default]
+    AcrossSections,
+    // Combines copies of a card within each section, so the main deck and sideboard keep their own counts.
+    WithinSections,
+}
+
+// Entries are kept in the order they appear in the deck list, the same card can appear more than once.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeckList {
+    pub entries: Vec<DeckListEntry>,
+    // Problems found while parsing, in the order of the lines they refer to.
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+impl DeckList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, entry: DeckListEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DeckListEntry> {
+        self.entries.iter()
+    }
+
+    pub fn section(&self, section: DeckSection) -> impl Iterator<Item = &DeckListEntry> {
+        self.entries.iter().filter(move |entry| entry.section == section)
+    }
+
+    // Combines entries for the same card and finish by adding up their counts, keeping the order of first appearance.
+    // Deck lists often list a card more than once, such as in several categories or in both the main deck and sideboard.
+    pub fn merge_duplicates(self, merge: MergeDuplicates) -> Self {
+        let mut merged_entries: Vec<DeckListEntry> = Vec::new();
+        let mut entry_indices: HashMap<(CollectionCardIdentifier, CardFinish, Option<DeckSection>), usize> = HashMap::new();
+
+        for entry in self.entries {
+            let section_key = match merge {
+                MergeDuplicates::AcrossSections => None,
+                MergeDuplicates::WithinSections => Some(entry.section),
+            };
+
+            match entry_indices.get(&(entry.identifier.clone(), entry.finish, section_key)) {
+                Some(&index) => {
+                    let merged_entry = &mut merged_entries[index];
+                    merged_entry.count += entry.count;
+
+                    for annotation in entry.annotations {
+                        if !merged_entry.annotations.contains(&annotation) {
+                            merged_entry.annotations.push(annotation);
+                        }
+                    }
+                },
+                None => {
+                    entry_indices.insert((entry.identifier.clone(), entry.finish, section_key), merged_entries.len());
+                    merged_entries.push(entry);
+                },
+            }
+        }
+
+        Self { entries: merged_entries, diagnostics: self.diagnostics }
+    }
+
+    // Total count of each card in the given sections.
+    pub fn card_counts(&self, sections: &[DeckSection]) -> HashMap<CollectionCardIdentifier, usize> {
+        let mut card_counts = HashMap::new();
+
+        for entry in self.entries.iter().filter(|entry| sections.contains(&entry.section)) {
+            *card_counts.entry(entry.identifier.clone()).or_insert(0) += entry.count;
+        }
+
+        card_counts
+    }
+}
+
+impl FromIterator<DeckListEntry> for DeckList {
+    fn from_iter<T: IntoIterator<Item = DeckListEntry>>(iter: T) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl IntoIterator for DeckList {
+    type Item = DeckListEntry;
+    type IntoIter = alloc::vec::IntoIter<DeckListEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a DeckList {
+    type Item = &'a DeckListEntry;
+    type IntoIter = core::slice::Iter<'a, DeckListEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
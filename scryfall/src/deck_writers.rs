@@ -0,0 +1,297 @@
+use alloc::{borrow::ToOwned, format, string::String, vec, vec::Vec};
+use hashbrown::HashMap;
+use log::warn;
+use uuid::Uuid;
+
+use crate::{api_interface::api_classes::{Card, CardDigest, Deck, DeckEntry, DeckImageUris, Finish}, csv, fetch_card_data::ResolvedCard, xml};
+
+// Magic Online only imports cards by their catalogue ID, so cards without one are left out.
+pub fn write_dek(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    let mut dek_data = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+<Deck xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n  \
+<NetDeckID>0</NetDeckID>\n  \
+<PreconstructedDeckID>0</PreconstructedDeckID>\n");
+
+    for (cards, is_sideboard) in [(main_deck, false), (sideboard, true)] {
+        for resolved_card in cards {
+            let Some(mtgo_id) = resolved_card.card.mtgo_id else {
+                warn!("{} is not available on Magic Online, leaving it out of the deck file", resolved_card.card);
+                continue;
+            };
+
+            dek_data += &format!(
+                "  <Cards CatID=\"{mtgo_id}\" Quantity=\"{}\" Sideboard=\"{is_sideboard}\" Name=\"{}\" Annotation=\"0\" />\n",
+                resolved_card.count,
+                xml::escape(&resolved_card.card.name),
+            );
+        }
+    }
+
+    dek_data += "</Deck>\n";
+
+    dek_data
+}
+
+// Cards are written with their printing, which Cockatrice uses to pick the card art.
+pub fn write_cod(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    let mut cod_data = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<cockatrice_deck version=\"1\">\n    <deckname></deckname>\n    <comments></comments>\n");
+
+    for (cards, zone_name) in [(main_deck, "main"), (sideboard, "side")] {
+        if cards.is_empty() {
+            continue;
+        }
+
+        cod_data += &format!("    <zone name=\"{zone_name}\">\n");
+        for resolved_card in cards {
+            cod_data += &format!(
+                "        <card number=\"{}\" name=\"{}\" setShortName=\"{}\" collectorNumber=\"{}\"/>\n",
+                resolved_card.count,
+                xml::escape(&resolved_card.card.name),
+                xml::escape(&resolved_card.card.set.to_ascii_uppercase()),
+                xml::escape(&resolved_card.card.collector_number),
+            );
+        }
+        cod_data += "    </zone>\n";
+    }
+
+    cod_data += "</cockatrice_deck>\n";
+
+    cod_data
+}
+
+pub fn write_forge_dck(deck_name: &str, main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    let mut dck_data = format!("[metadata]\nName={deck_name}\n");
+
+    for (cards, header) in [(main_deck, "[Main]"), (sideboard, "[Sideboard]")] {
+        if cards.is_empty() {
+            continue;
+        }
+
+        dck_data += header;
+        dck_data += "\n";
+        for resolved_card in cards {
+            dck_data += &format!("{} {}|{}\n", resolved_card.count, resolved_card.card.name, resolved_card.card.set.to_ascii_uppercase());
+        }
+    }
+
+    dck_data
+}
+
+pub fn write_xmage_dck(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    let mut dck_data = String::new();
+
+    for (cards, prefix) in [(main_deck, ""), (sideboard, "SB: ")] {
+        for resolved_card in cards {
+            dck_data += &format!("{prefix}{} [{}:{}] {}\n", resolved_card.count, resolved_card.card.set.to_ascii_uppercase(), resolved_card.card.collector_number, resolved_card.card.name);
+        }
+    }
+
+    dck_data
+}
+
+// Both text formats use the headers MTG Arena exports, which every deck list parser here understands.
+fn write_txt(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard], write_card: impl Fn(&ResolvedCard) -> String) -> String {
+    let mut txt_data = String::new();
+
+    for (cards, header) in [(main_deck, "Deck"), (sideboard, "Sideboard")] {
+        if cards.is_empty() {
+            continue;
+        }
+
+        if !txt_data.is_empty() {
+            txt_data += "\n";
+        }
+        txt_data += header;
+        txt_data += "\n";
+        for resolved_card in cards {
+            txt_data += &write_card(resolved_card);
+            txt_data += "\n";
+        }
+    }
+
+    txt_data
+}
+
+pub fn write_arena_txt(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    write_txt(main_deck, sideboard, |resolved_card| format!("{} {} ({}) {}", resolved_card.count, resolved_card.card.name, resolved_card.card.set.to_ascii_uppercase(), resolved_card.card.collector_number))
+}
+
+pub fn write_set_prefixed_txt(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    write_txt(main_deck, sideboard, |resolved_card| format!("{} [{}#{}] {}", resolved_card.count, resolved_card.card.set.to_ascii_uppercase(), resolved_card.card.collector_number, resolved_card.card.name))
+}
+
+// The digest holds what Scryfall shows in its deck builder, double-faced cards take their details from the front face.
+fn card_digest(card: &Card) -> CardDigest {
+    let front_face = card.card_faces.as_ref().and_then(|faces| faces.first());
+    let face_image = |index: usize| card.card_faces.as_ref()
+        .and_then(|faces| faces.get(index))
+        .and_then(|face| face.image_uris.as_ref())
+        .map(|image_uris| image_uris.normal.clone());
+
+    CardDigest {
+        id: card.id,
+        oracle_id: card.oracle_id.or_else(|| front_face.and_then(|face| face.oracle_id)).unwrap_or_default(),
+        name: card.name.clone(),
+        scryfall_uri: card.scryfall_uri.clone(),
+        mana_cost: card.mana_cost.clone().or_else(|| front_face.map(|face| face.mana_cost.clone())).unwrap_or_default(),
+        type_line: card.type_line.clone().or_else(|| front_face.and_then(|face| face.type_line.clone())).unwrap_or_default(),
+        collector_number: card.collector_number.clone(),
+        set: card.set.clone(),
+        image_uris: DeckImageUris {
+            front: card.image_uris.as_ref().map(|image_uris| image_uris.normal.clone()).or_else(|| face_image(0)).unwrap_or_default(),
+            back: card.image_uris.is_none().then(|| face_image(1)).flatten(),
+        },
+    }
+}
+
+// Written in the shape the API returns decks in, with nil IDs as the deck only exists locally.
+pub fn write_scryfall_json(deck_name: &str, main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    let mut entries = HashMap::new();
+
+    for (cards, section) in [(main_deck, "mainboard"), (sideboard, "sideboard")] {
+        let section_entries: Vec<DeckEntry> = cards.iter().enumerate().map(|(index, resolved_card)| DeckEntry {
+            id: Uuid::nil(),
+            deck_id: Uuid::nil(),
+            section: section.to_owned(),
+            cardinality: index as f32 + 1.0,
+            count: resolved_card.count,
+            raw_text: format!("{} {}", resolved_card.count, resolved_card.card.name),
+            found: true,
+            printing_specified: true,
+            finish: Some(Finish::NoFinish(false)),
+            card_digest: Some(card_digest(&resolved_card.card)),
+        }).collect();
+
+        entries.insert(section.to_owned(), section_entries);
+    }
+
+    let deck = Deck {
+        id: Uuid::nil(),
+        name: deck_name.to_owned(),
+        format: "casual".to_owned(),
+        layout: "constructed".to_owned(),
+        uri: String::new(),
+        scryfall_uri: String::new(),
+        description: None,
+        trashed: false,
+        in_compliance: true,
+        sections: HashMap::from([("primary".to_owned(), vec!["mainboard".to_owned()]), ("secondary".to_owned(), vec!["sideboard".to_owned()])]),
+        entries,
+    };
+
+    serde_json::to_string_pretty(&deck).expect("Decks should always serialise to JSON")
+}
+
+// The column names are ones the CSV parser recognises, with the Scryfall ID pinning the exact printing.
+pub fn write_csv(main_deck: &[ResolvedCard], sideboard: &[ResolvedCard]) -> String {
+    let mut csv_data = String::from("Count,Name,Set code,Collector number,Scryfall ID,Section\n");
+
+    for (cards, section) in [(main_deck, "Main"), (sideboard, "Sideboard")] {
+        for resolved_card in cards {
+            csv_data += &format!(
+                "{},{},{},{},{},{section}\n",
+                resolved_card.count,
+                csv::escape(&resolved_card.card.name, ','),
+                csv::escape(&resolved_card.card.set.to_ascii_uppercase(), ','),
+                csv::escape(&resolved_card.card.collector_number, ','),
+                resolved_card.card.id,
+            );
+        }
+    }
+
+    csv_data
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, vec};
+    use super::*;
+    use crate::{api_interface::{api_classes::Card, collection_card_identifier::CollectionCardIdentifier}, deck_list::{DeckListEntry, DeckSection}, deck_parsers::{parse_cod_data, parse_csv_data, parse_dck_data, parse_dek_data, parse_json_data, parse_txt_data}, test_cards::test_card};
+
+    #[test]
+    fn test_cod_round_trip() {
+        let main_deck = vec![ResolvedCard { count: 4, card: test_card(1, "Opt", "XLN", "65") }, ResolvedCard { count: 1, card: test_card(2, "Fire // Ice", "MH2", "290") }];
+        let sideboard = vec![ResolvedCard { count: 2, card: test_card(3, "Lightning Bolt", "M10", "146") }];
+
+        let deck_list = parse_cod_data(&write_cod(&main_deck, &sideboard)).expect("Written deck file should be valid");
+
+        let printing = |set: &str, collector_number: &str| CollectionCardIdentifier::CollectorNumberSet { collector_number: collector_number.to_owned(), set: set.to_owned() };
+        assert_eq!(deck_list.entries, vec![
+            DeckListEntry::new(printing("XLN", "65"), 4),
+            DeckListEntry::new(printing("MH2", "290"), 1),
+            DeckListEntry { section: DeckSection::Sideboard, ..DeckListEntry::new(printing("M10", "146"), 2) },
+        ]);
+        assert!(deck_list.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_dck_round_trips() {
+        let main_deck = vec![ResolvedCard { count: 4, card: test_card(1, "Opt", "XLN", "65") }];
+        let sideboard = vec![ResolvedCard { count: 2, card: test_card(3, "Lightning Bolt", "M10", "146") }];
+
+        let forge_deck_list = parse_dck_data(&write_forge_dck("Proxies", &main_deck, &sideboard));
+        assert_eq!(forge_deck_list.entries, vec![
+            DeckListEntry::new(CollectionCardIdentifier::NameSet { name: "Opt".to_owned(), set: "XLN".to_owned() }, 4),
+            DeckListEntry { section: DeckSection::Sideboard, ..DeckListEntry::new(CollectionCardIdentifier::NameSet { name: "Lightning Bolt".to_owned(), set: "M10".to_owned() }, 2) },
+        ]);
+
+        let xmage_deck_list = parse_dck_data(&write_xmage_dck(&main_deck, &sideboard));
+        assert_eq!(xmage_deck_list.entries, vec![
+            DeckListEntry::new(CollectionCardIdentifier::CollectorNumberSet { collector_number: "65".to_owned(), set: "XLN".to_owned() }, 4),
+            DeckListEntry { section: DeckSection::Sideboard, ..DeckListEntry::new(CollectionCardIdentifier::CollectorNumberSet { collector_number: "146".to_owned(), set: "M10".to_owned() }, 2) },
+        ]);
+    }
+
+    #[test]
+    fn test_resolved_deck_round_trips() {
+        let main_deck = vec![ResolvedCard { count: 4, card: test_card(1, "Opt", "XLN", "65") }, ResolvedCard { count: 1, card: test_card(2, "Ghired, Conclave Exile", "C20", "8") }];
+        let sideboard = vec![ResolvedCard { count: 2, card: test_card(3, "Lightning Bolt", "M10", "146") }];
+
+        let printing = |set: &str, collector_number: &str| CollectionCardIdentifier::CollectorNumberSet { collector_number: collector_number.to_owned(), set: set.to_owned() };
+        let printings = vec![
+            DeckListEntry::new(printing("XLN", "65"), 4),
+            DeckListEntry::new(printing("C20", "8"), 1),
+            DeckListEntry { section: DeckSection::Sideboard, ..DeckListEntry::new(printing("M10", "146"), 2) },
+        ];
+
+        let arena_deck_list = parse_txt_data(&write_arena_txt(&main_deck, &sideboard));
+        assert_eq!(arena_deck_list.entries, printings);
+        assert!(arena_deck_list.diagnostics.is_empty());
+
+        let set_prefixed_deck_list = parse_txt_data(&write_set_prefixed_txt(&main_deck, &sideboard));
+        assert_eq!(set_prefixed_deck_list.entries, printings);
+        assert!(set_prefixed_deck_list.diagnostics.is_empty());
+
+        let id = |id| CollectionCardIdentifier::Id { id: Uuid::from_u128(id) };
+        let ids = vec![
+            DeckListEntry::new(id(1), 4),
+            DeckListEntry::new(id(2), 1),
+            DeckListEntry { section: DeckSection::Sideboard, ..DeckListEntry::new(id(3), 2) },
+        ];
+
+        let json_deck_list = parse_json_data(&write_scryfall_json("Proxies", &main_deck, &sideboard)).expect("Written deck JSON should be valid");
+        assert_eq!(json_deck_list.entries, ids);
+
+        let csv_deck_list = parse_csv_data(&write_csv(&main_deck, &sideboard)).expect("Written CSV should be valid");
+        assert_eq!(csv_deck_list.entries, ids);
+        assert!(csv_deck_list.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_dek_round_trip() {
+        let with_mtgo_id = |mtgo_id, count, name| ResolvedCard { count, card: Card { mtgo_id: Some(mtgo_id), ..test_card(mtgo_id as u128, name, "XLN", "1") } };
+
+        let main_deck = vec![with_mtgo_id(65520, 4, "Opt"), with_mtgo_id(31745, 2, "Lim-D\u{fb}l's Vault & Friends")];
+        let sideboard = vec![with_mtgo_id(33458, 1, "Lightning Bolt"), ResolvedCard { count: 1, card: test_card(4, "Jace Beleren", "M10", "58") }];
+
+        let deck_list = parse_dek_data(&write_dek(&main_deck, &sideboard)).expect("Written deck file should be valid");
+
+        let mtgo_id = |mtgo_id| CollectionCardIdentifier::MtgoId { mtgo_id };
+        assert_eq!(deck_list.entries, vec![
+            DeckListEntry::new(mtgo_id(65520), 4),
+            DeckListEntry::new(mtgo_id(31745), 2),
+            DeckListEntry { section: DeckSection::Sideboard, ..DeckListEntry::new(mtgo_id(33458), 1) },
+        ]);
+        assert!(deck_list.diagnostics.is_empty());
+    }
+}
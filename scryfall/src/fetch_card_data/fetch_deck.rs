@@ -1,23 +1,177 @@
 use alloc::{boxed::Box, vec::Vec};
 use core::error::Error;
 use hashbrown::HashMap;
+use uuid::Uuid;
 
-use crate::api_interface::{collection_card_identifier::CollectionCardIdentifier, ApiInterface, RequestClient};
+use crate::{api_interface::{api_classes::Card, collection_card_identifier::CollectionCardIdentifier, ApiInterface, RequestClient}, deck_list::{DeckList, DeckSection}};
 use super::{fetch_cards_bulk::FetchCardsBulk, fetch_tokens::FetchRelatedTokens, resolve_card_counts::get_counts_for_cards, ResolvedCard};
 
+// Cards in the maybeboard are only being considered for the deck, so they are left out.
+const DECK_SECTIONS: [DeckSection; 4] = [DeckSection::Commander, DeckSection::Companion, DeckSection::Main, DeckSection::Sideboard];
+const MAIN_DECK_SECTIONS: [DeckSection; 2] = [DeckSection::Commander, DeckSection::Main];
+const SIDEBOARD_SECTIONS: [DeckSection; 2] = [DeckSection::Companion, DeckSection::Sideboard];
+
+// A deck resolved with its main deck and sideboard apart, as deck files keep them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolvedDeck {
+    pub main_deck: Vec<ResolvedCard>,
+    pub sideboard: Vec<ResolvedCard>,
+    pub tokens: Vec<ResolvedCard>,
+}
+
+// Copies of the same printing are added up into its first entry.
+fn add_up_copies<'a>(resolved_cards: impl Iterator<Item = &'a ResolvedCard>) -> Vec<ResolvedCard> {
+    let mut cards: Vec<ResolvedCard> = Vec::new();
+    let mut card_indices: HashMap<Uuid, usize> = HashMap::new();
+
+    for resolved_card in resolved_cards {
+        match card_indices.get(&resolved_card.card.id) {
+            Some(&index) => cards[index].count += resolved_card.count,
+            None => {
+                card_indices.insert(resolved_card.card.id, cards.len());
+                cards.push(resolved_card.clone());
+            },
+        }
+    }
+
+    cards
+}
+
+impl ResolvedDeck {
+    // Every card to print, with copies in both the main deck and sideboard added up.
+    pub fn cards(&self) -> Vec<ResolvedCard> {
+        add_up_copies(self.main_deck.iter().chain(&self.sideboard).chain(&self.tokens))
+    }
+
+    // Swaps every copy of a printing for another, such as one picked on the website.
+    pub fn replace_printing(&mut self, old_id: Uuid, new_card: &Card) {
+        for resolved_cards in [&mut self.main_deck, &mut self.sideboard, &mut self.tokens] {
+            for resolved_card in resolved_cards.iter_mut().filter(|resolved_card| resolved_card.card.id == old_id) {
+                resolved_card.card = new_card.clone();
+            }
+
+            *resolved_cards = add_up_copies(resolved_cards.iter());
+        }
+    }
+}
+
 pub trait FetchDeck {
-    fn fetch_deck(&self, deck_list: &HashMap<CollectionCardIdentifier, usize>, fetch_related_tokens: bool) -> impl Future<Output = Result<Vec<ResolvedCard>, Box<dyn Error>>>;
+    fn fetch_deck(&self, deck_list: &DeckList, fetch_related_tokens: bool) -> impl Future<Output = Result<Vec<ResolvedCard>, Box<dyn Error>>>;
+    fn fetch_deck_sections(&self, deck_list: &DeckList, fetch_related_tokens: bool) -> impl Future<Output = Result<ResolvedDeck, Box<dyn Error>>>;
 }
 
 impl<Client: RequestClient> FetchDeck for ApiInterface<Client> {
-    async fn fetch_deck(&self, deck_list: &HashMap<CollectionCardIdentifier, usize>, fetch_related_tokens: bool) -> Result<Vec<ResolvedCard>, Box<dyn Error>> {
-        let card_list: Vec<CollectionCardIdentifier> = deck_list.keys().cloned().collect();
+    async fn fetch_deck(&self, deck_list: &DeckList, fetch_related_tokens: bool) -> Result<Vec<ResolvedCard>, Box<dyn Error>> {
+        let card_counts = deck_list.card_counts(&DECK_SECTIONS);
+        let card_list: Vec<CollectionCardIdentifier> = card_counts.keys().cloned().collect();
         let mut cards = self.fetch_cards_bulk(&card_list).await?;
 
         if fetch_related_tokens {
             cards.append(&mut self.fetch_related_tokens(&cards).await?);
         }
 
-        Ok(get_counts_for_cards(deck_list, cards))
+        Ok(get_counts_for_cards(&card_counts, cards))
     }
-}
\ No newline at end of file
+
+    // The companion is kept in the sideboard, where deck files put it.
+    async fn fetch_deck_sections(&self, deck_list: &DeckList, fetch_related_tokens: bool) -> Result<ResolvedDeck, Box<dyn Error>> {
+        let mut resolved_deck = ResolvedDeck::default();
+
+        for (sections, resolved_cards) in [(MAIN_DECK_SECTIONS, &mut resolved_deck.main_deck), (SIDEBOARD_SECTIONS, &mut resolved_deck.sideboard)] {
+            let card_counts = deck_list.card_counts(&sections);
+            let card_list: Vec<CollectionCardIdentifier> = card_counts.keys().cloned().collect();
+            *resolved_cards = get_counts_for_cards(&card_counts, self.fetch_cards_bulk(&card_list).await?);
+        }
+
+        if fetch_related_tokens {
+            let cards: Vec<_> = resolved_deck.main_deck.iter().chain(&resolved_deck.sideboard).map(|resolved_card| resolved_card.card.clone()).collect();
+            let tokens = self.fetch_related_tokens(&cards).await?;
+            resolved_deck.tokens = get_counts_for_cards(&deck_list.card_counts(&DECK_SECTIONS), tokens);
+        }
+
+        Ok(resolved_deck)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, string::ToString, vec, vec::Vec};
+    use futures::executor::block_on;
+    use super::*;
+    use crate::{deck_list::DeckListEntry, test_cards::{fixture_interface, test_card}};
+
+    fn name(name: &str) -> CollectionCardIdentifier {
+        CollectionCardIdentifier::Name { name: name.to_owned() }
+    }
+
+    #[test]
+    fn test_fetch_deck_fuzzy_fallback() {
+        let interface = fixture_interface();
+        let deck_list = DeckList::from_iter([DeckListEntry::new(name("Opt"), 4), DeckListEntry::new(name("Lightning Bolt"), 3), DeckListEntry::new(name("Jace Belern"), 1)]);
+
+        let mut resolved = block_on(interface.fetch_deck(&deck_list, false)).expect("Deck should resolve from fixtures");
+        resolved.sort_by(|first, second| first.card.name.cmp(&second.card.name));
+
+        let counts: Vec<(usize, &str)> = resolved.iter().map(|card| (card.count, card.card.name.as_str())).collect();
+        assert_eq!(counts, vec![(1, "Jace Beleren"), (3, "Lightning Bolt"), (4, "Opt")]);
+    }
+
+    #[test]
+    fn test_fetch_deck_not_found() {
+        let interface = fixture_interface();
+        let deck_list = DeckList::from_iter([DeckListEntry::new(name("Opt"), 4), DeckListEntry::new(name("Not A Real Card"), 1)]);
+
+        let error = block_on(interface.fetch_deck(&deck_list, false)).expect_err("Unknown cards should fail to resolve");
+        assert!(error.to_string().contains("Not A Real Card"));
+    }
+
+    #[test]
+    fn test_fetch_deck_sections() {
+        let interface = fixture_interface();
+        let in_section = |section: DeckSection, identifier: CollectionCardIdentifier, count: usize| DeckListEntry { section, ..DeckListEntry::new(identifier, count) };
+        let deck_list = DeckList::from_iter([
+            in_section(DeckSection::Main, name("Opt"), 3),
+            in_section(DeckSection::Sideboard, name("Opt"), 1),
+            in_section(DeckSection::Maybeboard, name("Lightning Bolt"), 2),
+        ]);
+
+        // Copies in different sections add up, while the maybeboard is not resolved at all.
+        let resolved = block_on(interface.fetch_deck(&deck_list, false)).expect("Deck should resolve from fixtures");
+        let counts: Vec<(usize, &str)> = resolved.iter().map(|card| (card.count, card.card.name.as_str())).collect();
+        assert_eq!(counts, vec![(4, "Opt")]);
+    }
+    #[test]
+    fn test_fetch_deck_sections_apart() {
+        let interface = fixture_interface();
+        let in_section = |section: DeckSection, identifier: CollectionCardIdentifier, count: usize| DeckListEntry { section, ..DeckListEntry::new(identifier, count) };
+        let deck_list = DeckList::from_iter([
+            in_section(DeckSection::Main, name("Opt"), 4),
+            in_section(DeckSection::Sideboard, name("Opt"), 1),
+            in_section(DeckSection::Sideboard, name("Lightning Bolt"), 2),
+            in_section(DeckSection::Maybeboard, name("Jace Belern"), 1),
+        ]);
+
+        let resolved_deck = block_on(interface.fetch_deck_sections(&deck_list, false)).expect("Deck should resolve from fixtures");
+        let counts = |cards: &[ResolvedCard]| -> Vec<(usize, String)> {
+            let mut counts: Vec<(usize, String)> = cards.iter().map(|card| (card.count, card.card.name.clone())).collect();
+            counts.sort_by(|first, second| first.1.cmp(&second.1));
+            counts
+        };
+        assert_eq!(counts(&resolved_deck.main_deck), vec![(4, "Opt".to_owned())]);
+        assert_eq!(counts(&resolved_deck.sideboard), vec![(2, "Lightning Bolt".to_owned()), (1, "Opt".to_owned())]);
+        assert_eq!(counts(&resolved_deck.cards()), vec![(2, "Lightning Bolt".to_owned()), (5, "Opt".to_owned())]);
+    }
+    #[test]
+    fn test_replace_printing() {
+        let mut resolved_deck = ResolvedDeck {
+            main_deck: vec![ResolvedCard { count: 3, card: test_card(1, "Opt", "XLN", "65") }, ResolvedCard { count: 1, card: test_card(2, "Opt", "DOM", "60") }],
+            sideboard: vec![ResolvedCard { count: 1, card: test_card(1, "Opt", "XLN", "65") }],
+            tokens: Vec::new(),
+        };
+
+        // Copies that end up as the same printing are added up, in each section separately.
+        resolved_deck.replace_printing(Uuid::from_u128(1), &test_card(2, "Opt", "DOM", "60"));
+        assert_eq!(resolved_deck.main_deck, vec![ResolvedCard { count: 4, card: test_card(2, "Opt", "DOM", "60") }]);
+        assert_eq!(resolved_deck.sideboard, vec![ResolvedCard { count: 1, card: test_card(2, "Opt", "DOM", "60") }]);
+    }
+}
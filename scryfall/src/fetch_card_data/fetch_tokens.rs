@@ -38,3 +38,28 @@ impl<Client: RequestClient> FetchRelatedTokens for ApiInterface<Client> {
         Ok(token_oracle_ids.into_values().collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, vec};
+    use futures::executor::block_on;
+    use super::*;
+    use crate::{deck_list::{DeckList, DeckListEntry}, fetch_card_data::fetch_deck::FetchDeck, test_cards::fixture_interface};
+
+    #[test]
+    fn test_fetch_related_tokens() {
+        let interface = fixture_interface();
+        let deck_list = DeckList::from_iter([DeckListEntry::new(CollectionCardIdentifier::Name { name: "Young Pyromancer".to_owned() }, 1)]);
+
+        let cards: Vec<Card> = block_on(interface.fetch_deck(&deck_list, false)).expect("Deck should resolve from fixtures")
+            .into_iter().map(|resolved_card| resolved_card.card).collect();
+
+        // The card itself is also listed among its related parts, but only tokens are fetched.
+        let tokens = block_on(interface.fetch_related_tokens(&cards)).expect("Tokens should resolve from fixtures");
+        assert_eq!(tokens.iter().map(|token| token.name.as_str()).collect::<Vec<&str>>(), vec!["Elemental"]);
+
+        let with_tokens = block_on(interface.fetch_deck(&deck_list, true)).expect("Deck with tokens should resolve from fixtures");
+        assert_eq!(with_tokens.len(), 2);
+        assert!(with_tokens.iter().all(|resolved_card| resolved_card.count == 1));
+    }
+}
@@ -1,10 +1,18 @@
 #![cfg_attr(not(feature = "std"), no_std)]
-#![feature(test)]
+#![cfg_attr(test, feature(test))]
 extern crate alloc;
 
 pub mod api_interface;
 pub mod deck_diff;
+pub mod deck_list;
 pub mod deck_parsers;
+pub mod deck_writers;
 pub mod card_images_helper;
+pub mod csv;
+pub mod tabletop_simulator;
+pub mod text_proxies;
 pub mod token_handling;
-pub mod fetch_card_data;
\ No newline at end of file
+pub mod fetch_card_data;
+pub mod xml;
+#[cfg(test)]
+mod test_cards;
@@ -0,0 +1,297 @@
+use alloc::{borrow::ToOwned, collections::BTreeMap, format, string::{String, ToString}, vec::Vec};
+use hashbrown::HashMap;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{api_interface::api_classes::Card, card_images_helper::{extract_images, ImageUriType}, fetch_card_data::ResolvedCard};
+
+pub const SHEET_COLUMNS: usize = 10;
+pub const SHEET_ROWS: usize = 7;
+// Tabletop Simulator shows the last image of a sheet for hidden cards, so that slot is left empty.
+pub const CARDS_PER_SHEET: usize = SHEET_COLUMNS * SHEET_ROWS - 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtsFace {
+    pub name: String,
+    pub description: String,
+    pub image: String,
+}
+
+// Double-faced cards have a face for each side, which Tabletop Simulator switches between as states of one card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtsCard {
+    pub count: usize,
+    pub faces: Vec<TtsFace>,
+}
+
+// How the face images are given to Tabletop Simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtsImages<'a> {
+    // Every face image is its own custom deck, loaded straight from its URL.
+    Individual,
+    // The face images are stitched into sheets in the order of tts_contact_sheets, found at these URLs.
+    ContactSheets(&'a [String]),
+}
+
+pub fn tts_cards(cards: &[ResolvedCard], image_type: ImageUriType) -> Vec<TtsCard> {
+    let mut counts: HashMap<Uuid, usize> = HashMap::new();
+    for resolved_card in cards {
+        *counts.entry(resolved_card.card.id).or_default() += resolved_card.count;
+    }
+
+    let mut unique_cards: Vec<Card> = Vec::new();
+    for resolved_card in cards {
+        if !unique_cards.iter().any(|card| card.id == resolved_card.card.id) {
+            unique_cards.push(resolved_card.card.clone());
+        }
+    }
+
+    extract_images(unique_cards, false, image_type).into_iter().map(|(card, images)| {
+        let count = counts.get(&card.id).copied().unwrap_or(1);
+
+        // Cards whose faces share one image, such as split cards, become a single face with the text of both.
+        let faces = match &card.card_faces {
+            Some(card_faces) if card_faces.len() == images.len() => card_faces.iter().zip(images).map(|(card_face, image)| TtsFace {
+                name: card_face.name.clone(),
+                description: card_face.oracle_text.clone().unwrap_or_default(),
+                image,
+            }).collect(),
+            _ => images.into_iter().take(1).map(|image| TtsFace {
+                name: card.name.clone(),
+                description: card_description(&card),
+                image,
+            }).collect(),
+        };
+
+        TtsCard { count, faces }
+    }).collect()
+}
+
+fn card_description(card: &Card) -> String {
+    if let Some(oracle_text) = &card.oracle_text {
+        return oracle_text.clone();
+    }
+
+    card.card_faces.iter().flatten()
+        .filter_map(|card_face| card_face.oracle_text.as_deref())
+        .collect::<Vec<&str>>()
+        .join("\n//\n")
+}
+
+// Each image only needs to appear once, however many copies of the card there are.
+fn unique_images(cards: &[&[TtsCard]]) -> Vec<String> {
+    let mut images: Vec<String> = Vec::new();
+
+    for face in cards.iter().copied().flatten().flat_map(|card| &card.faces) {
+        if !images.contains(&face.image) {
+            images.push(face.image.clone());
+        }
+    }
+
+    images
+}
+
+// The images to stitch into each sheet, row by row from the top left.
+pub fn tts_contact_sheets(cards: &[&[TtsCard]]) -> Vec<Vec<String>> {
+    unique_images(cards).chunks(CARDS_PER_SHEET).map(<[String]>::to_vec).collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Transform {
+    pos_x: f32,
+    pos_y: f32,
+    pos_z: f32,
+    rot_x: f32,
+    rot_y: f32,
+    rot_z: f32,
+    scale_x: f32,
+    scale_y: f32,
+    scale_z: f32,
+}
+
+impl Transform {
+    // Decks are placed face down, side by side.
+    fn face_down(pos_x: f32) -> Self {
+        Self { pos_x, pos_y: 1.0, pos_z: 0.0, rot_x: 0.0, rot_y: 180.0, rot_z: 180.0, scale_x: 1.0, scale_y: 1.0, scale_z: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct CustomDeck {
+    #[serde(rename = "FaceURL")]
+    face_url: String,
+    #[serde(rename = "BackURL")]
+    back_url: String,
+    num_width: usize,
+    num_height: usize,
+    back_is_hidden: bool,
+    unique_back: bool,
+    r#type: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct TtsObject {
+    name: &'static str,
+    transform: Transform,
+    nickname: String,
+    description: String,
+    #[serde(rename = "CardID", skip_serializing_if = "Option::is_none")]
+    card_id: Option<usize>,
+    #[serde(rename = "DeckIDs", skip_serializing_if = "Vec::is_empty")]
+    deck_ids: Vec<usize>,
+    custom_deck: BTreeMap<String, CustomDeck>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    contained_objects: Vec<TtsObject>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    states: BTreeMap<String, TtsObject>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SavedObject {
+    object_states: Vec<TtsObject>,
+}
+
+struct DeckBuilder<'a> {
+    images: TtsImages<'a>,
+    unique_images: Vec<String>,
+    back_url: &'a str,
+}
+
+impl DeckBuilder<'_> {
+    // Card IDs are the key of their custom deck followed by two digits for their position on its sheet.
+    fn card_object(&self, face: &TtsFace, pos_x: f32) -> TtsObject {
+        let image_index = self.unique_images.iter().position(|image| *image == face.image).unwrap_or_default();
+
+        let (deck_key, face_url, slot, (num_width, num_height)) = match self.images {
+            TtsImages::Individual => (image_index + 1, face.image.clone(), 0, (1, 1)),
+            TtsImages::ContactSheets(sheet_urls) => {
+                let sheet_index = image_index / CARDS_PER_SHEET;
+                let face_url = sheet_urls.get(sheet_index).cloned().unwrap_or_default();
+                (sheet_index + 1, face_url, image_index % CARDS_PER_SHEET, (SHEET_COLUMNS, SHEET_ROWS))
+            },
+        };
+
+        let custom_deck = CustomDeck { face_url, back_url: self.back_url.to_owned(), num_width, num_height, back_is_hidden: true, unique_back: false, r#type: 0 };
+
+        TtsObject {
+            name: "Card",
+            transform: Transform::face_down(pos_x),
+            nickname: face.name.clone(),
+            description: face.description.clone(),
+            card_id: Some(deck_key * 100 + slot),
+            deck_ids: Vec::new(),
+            custom_deck: BTreeMap::from([(deck_key.to_string(), custom_deck)]),
+            contained_objects: Vec::new(),
+            states: BTreeMap::new(),
+        }
+    }
+
+    // Later faces become numbered states of the front face, starting from 2 as Tabletop Simulator does.
+    fn card_with_states(&self, card: &TtsCard, pos_x: f32) -> Option<TtsObject> {
+        let (front_face, other_faces) = card.faces.split_first()?;
+        let mut card_object = self.card_object(front_face, pos_x);
+
+        for (index, face) in other_faces.iter().enumerate() {
+            card_object.states.insert((index + 2).to_string(), self.card_object(face, pos_x));
+        }
+
+        Some(card_object)
+    }
+
+    // Tabletop Simulator only accepts decks of at least two cards, a single card is saved on its own.
+    fn deck_object(&self, nickname: &str, cards: &[TtsCard], pos_x: f32) -> Option<TtsObject> {
+        let card_objects: Vec<TtsObject> = cards.iter()
+            .filter_map(|card| Some((card.count, self.card_with_states(card, pos_x)?)))
+            .flat_map(|(count, card_object)| core::iter::repeat_n(card_object, count))
+            .collect();
+
+        if card_objects.len() <= 1 {
+            return card_objects.into_iter().next();
+        }
+
+        let deck_ids = card_objects.iter().filter_map(|card_object| card_object.card_id).collect();
+        let custom_deck = card_objects.iter()
+            .flat_map(|card_object| card_object.custom_deck.iter().chain(card_object.states.values().flat_map(|state| &state.custom_deck)))
+            .map(|(deck_key, custom_deck)| (deck_key.clone(), custom_deck.clone()))
+            .collect();
+
+        Some(TtsObject {
+            name: "DeckCustom",
+            transform: Transform::face_down(pos_x),
+            nickname: nickname.to_owned(),
+            description: String::new(),
+            card_id: None,
+            deck_ids,
+            custom_deck,
+            contained_objects: card_objects,
+            states: BTreeMap::new(),
+        })
+    }
+}
+
+// A saved object with the main deck and, next to it, the sideboard.
+pub fn write_tts_deck(deck_name: &str, main_deck: &[TtsCard], sideboard: &[TtsCard], images: TtsImages, back_url: &str) -> String {
+    let builder = DeckBuilder { images, unique_images: unique_images(&[main_deck, sideboard]), back_url };
+
+    let object_states = [(deck_name.to_owned(), main_deck, 0.0), (format!("{deck_name} sideboard"), sideboard, 3.0)].into_iter()
+        .filter_map(|(nickname, cards, pos_x)| builder.deck_object(&nickname, cards, pos_x))
+        .collect();
+
+    serde_json::to_string_pretty(&SavedObject { object_states }).expect("Saved objects should always serialise to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use serde_json::{from_str, Value};
+    use super::*;
+    use crate::{api_interface::api_classes::CardFace, card_images_helper::DEFAULT_CARD_BACK_URL, test_cards::test_card};
+
+    fn double_faced_card() -> Card {
+        let front = test_card(2, "Delver of Secrets", "ISD", "51");
+        let back = test_card(3, "Insectile Aberration", "ISD", "51");
+        let face = |card: &Card| CardFace {
+            image_uris: card.image_uris.clone(),
+            oracle_text: Some(format!("{} text", card.name)),
+            ..serde_json::from_value(serde_json::json!({ "object": "card_face", "name": card.name, "mana_cost": "" })).expect("Card face JSON should be valid")
+        };
+
+        Card { name: "Delver of Secrets // Insectile Aberration".to_owned(), image_uris: None, card_faces: Some(vec![face(&front), face(&back)]), ..front }
+    }
+
+    #[test]
+    fn test_tts_deck() {
+        let main_deck = tts_cards(&[
+            ResolvedCard { count: 2, card: Card { oracle_text: Some("Scry 1.\nDraw a card.".to_owned()), ..test_card(1, "Opt", "XLN", "65") } },
+            ResolvedCard { count: 1, card: double_faced_card() },
+        ], ImageUriType::Normal);
+        let sideboard = tts_cards(&[ResolvedCard { count: 1, card: test_card(4, "Lightning Bolt", "M10", "146") }], ImageUriType::Normal);
+
+        assert_eq!(main_deck[0].faces, vec![TtsFace { name: "Opt".to_owned(), description: "Scry 1.\nDraw a card.".to_owned(), image: format!("https://cards.scryfall.io/normal/front/{}.jpg", Uuid::from_u128(1)) }]);
+        assert_eq!(main_deck[1].faces.iter().map(|face| face.name.as_str()).collect::<Vec<_>>(), vec!["Delver of Secrets", "Insectile Aberration"]);
+
+        let saved_object: Value = from_str(&write_tts_deck("Proxies", &main_deck, &sideboard, TtsImages::Individual, DEFAULT_CARD_BACK_URL)).expect("Saved object should be valid JSON");
+        let [deck, single_card] = saved_object["ObjectStates"].as_array().expect("Saved object should have object states").as_slice() else {
+            panic!("Saved object should have a deck and a single card");
+        };
+
+        assert_eq!(deck["Name"], "DeckCustom");
+        assert_eq!(deck["DeckIDs"], serde_json::json!([100, 100, 200]));
+        assert_eq!(deck["CustomDeck"].as_object().map(|custom_deck| custom_deck.len()), Some(3));
+        assert_eq!(deck["ContainedObjects"][2]["Nickname"], "Delver of Secrets");
+        assert_eq!(deck["ContainedObjects"][2]["States"]["2"]["Nickname"], "Insectile Aberration");
+        assert_eq!(deck["ContainedObjects"][2]["States"]["2"]["CardID"], 300);
+        assert_eq!(single_card["Name"], "Card");
+        assert_eq!(single_card["CardID"], 400);
+
+        let sheet_urls = ["file:///sheet-1.jpg".to_owned()];
+        let sheets_object: Value = from_str(&write_tts_deck("Proxies", &main_deck, &sideboard, TtsImages::ContactSheets(&sheet_urls), DEFAULT_CARD_BACK_URL)).expect("Saved object should be valid JSON");
+        assert_eq!(sheets_object["ObjectStates"][0]["DeckIDs"], serde_json::json!([100, 100, 101]));
+        assert_eq!(sheets_object["ObjectStates"][0]["CustomDeck"]["1"]["NumWidth"], 10);
+        assert_eq!(tts_contact_sheets(&[&main_deck, &sideboard]).concat().len(), 4);
+    }
+}
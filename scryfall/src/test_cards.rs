@@ -0,0 +1,118 @@
+use alloc::{format, string::String};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_interface::{api_classes::Card, collection_card_identifier::CollectionCardIdentifier, fixture_clients::ReplayClient, ApiInterface};
+
+// Builds the JSON of a card with every field the API always returns, so tests can run without any network access.
+pub fn test_card_json(id: u128, name: &str, set: &str, collector_number: &str) -> Value {
+    let id = Uuid::from_u128(id);
+    let oracle_id = Uuid::from_u128(name.bytes().fold(0_u128, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u128)));
+    let lowercase_set: String = set.to_ascii_lowercase();
+
+    let image_uris = json!({
+        "small": format!("https://cards.scryfall.io/small/front/{id}.jpg"),
+        "normal": format!("https://cards.scryfall.io/normal/front/{id}.jpg"),
+        "large": format!("https://cards.scryfall.io/large/front/{id}.jpg"),
+        "art_crop": format!("https://cards.scryfall.io/art_crop/front/{id}.jpg"),
+        "border_crop": format!("https://cards.scryfall.io/border_crop/front/{id}.jpg"),
+        "png": format!("https://cards.scryfall.io/png/front/{id}.png"),
+    });
+
+    // The macro hits the recursion limit with every field at once, so the print fields are added separately.
+    let mut card = json!({
+        "object": "card",
+        "id": id,
+        "oracle_id": oracle_id,
+        "lang": "en",
+        "tcgplayer_id": null,
+        "layout": "normal",
+        "prints_search_uri": format!("https://api.scryfall.com/cards/search?order=released&q=oracleid%3A{oracle_id}&unique=prints"),
+        "rulings_uri": format!("https://api.scryfall.com/cards/{id}/rulings"),
+        "scryfall_uri": format!("https://scryfall.com/card/{lowercase_set}/{collector_number}"),
+        "uri": format!("https://api.scryfall.com/cards/{id}"),
+        "color_identity": [],
+        "keywords": [],
+        "legalities": {},
+        "name": name,
+        "reserved": false,
+        "type_line": "Instant",
+    });
+
+    let print_fields = json!({
+        "booster": true,
+        "border_color": "black",
+        "collector_number": collector_number,
+        "digital": false,
+        "finishes": ["nonfoil"],
+        "foil": false,
+        "frame": "2015",
+        "full_art": false,
+        "games": ["paper"],
+        "highres_image": true,
+        "image_status": "highres_scan",
+        "image_uris": image_uris,
+        "nonfoil": true,
+        "oversized": false,
+        "prices": {},
+        "promo": false,
+        "rarity": "common",
+        "related_uris": {},
+        "released_at": "2020-01-01",
+        "reprint": false,
+        "scryfall_set_uri": format!("https://scryfall.com/sets/{lowercase_set}"),
+        "set_name": set,
+        "set_search_uri": format!("https://api.scryfall.com/cards/search?q=e%3A{lowercase_set}"),
+        "set_type": "expansion",
+        "set_uri": format!("https://api.scryfall.com/sets/{lowercase_set}"),
+        "set": lowercase_set,
+        "set_id": Uuid::nil(),
+        "story_spotlight": false,
+        "textless": false,
+        "variation": false,
+    });
+
+    if let (Some(card), Value::Object(print_fields)) = (card.as_object_mut(), print_fields) {
+        card.extend(print_fields);
+    }
+
+    card
+}
+
+pub fn test_card(id: u128, name: &str, set: &str, collector_number: &str) -> Card {
+    serde_json::from_value(test_card_json(id, name, set, collector_number)).expect("Test card JSON should be a valid card")
+}
+
+// Replays responses for the decks in fixtures/deck_resolution.json:
+// Opt, Lightning Bolt and Young Pyromancer, whose Elemental token is related to it, resolve directly.
+// Jace Belern is only found by the fuzzy fallback and Not A Real Card is not found at all.
+// The committed fixtures were written by hand with sequential IDs, record_deck_resolution_fixtures replaces them with real responses.
+pub fn fixture_interface() -> ApiInterface<ReplayClient> {
+    let fixtures = include_str!("../fixtures/deck_resolution.json");
+    ApiInterface::from_client(ReplayClient::from_fixtures(fixtures).expect("Fixtures should be valid JSON"))
+}
+
+// Records fixtures/deck_resolution.json again from the live API, making every request the fixture tests make.
+// Run with `cargo test -p scryfall --features std record_deck_resolution_fixtures -- --ignored` when the API's responses change.
+#[cfg(feature = "std")]
+#[tokio::test]
+#[ignore = "sends requests to the live Scryfall API"]
+async fn record_deck_resolution_fixtures() {
+    use alloc::{borrow::ToOwned, vec::Vec};
+    use crate::{api_interface::{fixture_clients::RecordingClient, reqwest_wrapper::ReqwestWrapper}, deck_list::{DeckList, DeckListEntry}, fetch_card_data::fetch_deck::FetchDeck};
+
+    let interface = ApiInterface::<RecordingClient<ReqwestWrapper>>::builder().build().expect("HTTP client should initialise");
+    let name = |name: &str| CollectionCardIdentifier::Name { name: name.to_owned() };
+
+    let deck_list = DeckList::from_iter(["Opt", "Lightning Bolt", "Jace Belern", "Young Pyromancer"].map(|card_name| DeckListEntry::new(name(card_name), 1)));
+    let cards: Vec<Card> = interface.fetch_deck(&deck_list, true).await.expect("Deck should resolve from the API")
+        .into_iter().map(|resolved_card| resolved_card.card).collect();
+    assert!(cards.iter().any(|card| card.name == "Elemental"));
+
+    // The failed lookups are recorded too, so the tests can replay them.
+    let missing_deck_list = DeckList::from_iter([DeckListEntry::new(name("Not A Real Card"), 1)]);
+    interface.fetch_deck(&missing_deck_list, false).await.expect_err("Unknown cards should fail to resolve");
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/deck_resolution.json");
+    interface.http_client.save(&path).expect("Fixtures should be saved");
+}
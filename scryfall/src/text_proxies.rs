@@ -0,0 +1,405 @@
+use alloc::{borrow::ToOwned, format, string::String, vec, vec::Vec};
+
+use crate::{api_interface::api_classes::{Card, CardFace}, xml};
+
+pub const CARD_WIDTH_MM: f32 = 63.0;
+pub const CARD_HEIGHT_MM: f32 = 88.0;
+pub const MM_PER_POINT: f32 = 25.4 / 72.0;
+
+const MARGIN_MM: f32 = 3.0;
+const PADDING_MM: f32 = 1.5;
+pub const LINE_WIDTH_MM: f32 = 0.3;
+const TITLE_SIZE_PT: f32 = 9.0;
+const COST_SIZE_PT: f32 = 8.5;
+const TYPE_LINE_SIZE_PT: f32 = 7.5;
+const STATS_SIZE_PT: f32 = 9.0;
+const RULES_TEXT_SIZE_PT: f32 = 8.5;
+const MIN_TEXT_SIZE_PT: f32 = 4.0;
+const LINE_SPACING: f32 = 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontWeight {
+    Regular,
+    Bold,
+}
+
+// Which end of the text its position is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    End,
+}
+
+// Positions are in millimetres from the top left corner of the card, with text positioned by its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub x_mm: f32,
+    pub y_mm: f32,
+    pub size_pt: f32,
+    pub weight: FontWeight,
+    pub anchor: TextAnchor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRect {
+    pub x_mm: f32,
+    pub y_mm: f32,
+    pub width_mm: f32,
+    pub height_mm: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameLine {
+    pub from_mm: (f32, f32),
+    pub to_mm: (f32, f32),
+}
+
+// One printed side of a card, drawn as outlined boxes and lines of text.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextCard {
+    pub rects: Vec<FrameRect>,
+    pub lines: Vec<FrameLine>,
+    pub spans: Vec<TextSpan>,
+}
+
+// The gameplay text of a card or one of its faces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FaceText {
+    name: String,
+    mana_cost: String,
+    type_line: String,
+    oracle_text: String,
+    // Power and toughness, loyalty or defence, whichever the face has.
+    stats: Option<String>,
+}
+
+impl FaceText {
+    fn from_card(card: &Card) -> Self {
+        let stats = match (&card.power, &card.toughness) {
+            (Some(power), Some(toughness)) => Some(format!("{power}/{toughness}")),
+            _ => card.loyalty.clone().or_else(|| card.defence.clone()),
+        };
+
+        Self {
+            name: card.name.clone(),
+            mana_cost: card.mana_cost.clone().unwrap_or_default(),
+            type_line: card.type_line.clone().unwrap_or_default(),
+            oracle_text: card.oracle_text.clone().unwrap_or_default(),
+            stats,
+        }
+    }
+
+    fn from_face(face: &CardFace) -> Self {
+        let stats = match (&face.power, &face.toughness) {
+            (Some(power), Some(toughness)) => Some(format!("{power}/{toughness}")),
+            _ => face.loyalty.clone().or_else(|| face.defense.clone()),
+        };
+
+        Self {
+            name: face.name.clone(),
+            mana_cost: face.mana_cost.clone(),
+            type_line: face.type_line.clone().unwrap_or_default(),
+            oracle_text: face.oracle_text.clone().unwrap_or_default(),
+            stats,
+        }
+    }
+}
+
+// Helvetica's widths in thousandths of the font size, which Arial shares, for the printable ASCII characters.
+const REGULAR_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+fn character_width(character: char, weight: FontWeight) -> u16 {
+    let widths = match weight {
+        FontWeight::Regular => &REGULAR_WIDTHS,
+        FontWeight::Bold => &BOLD_WIDTHS,
+    };
+
+    match character {
+        ' '..='~' => widths[character as usize - ' ' as usize],
+        '—' => 1000,
+        '•' => 350,
+        '‘' | '’' => if weight == FontWeight::Bold { 278 } else { 222 },
+        '“' | '”' => if weight == FontWeight::Bold { 500 } else { 333 },
+        '−' => 584,
+        // Accented letters are about as wide as the average lowercase letter.
+        _ => 556,
+    }
+}
+
+pub fn text_width_mm(text: &str, size_pt: f32, weight: FontWeight) -> f32 {
+    let width: u32 = text.chars().map(|character| character_width(character, weight) as u32).sum();
+    width as f32 / 1000.0 * size_pt * MM_PER_POINT
+}
+
+// The largest size up to the preferred one at which the text fits on one line.
+fn fit_line(text: &str, max_width_mm: f32, preferred_size_pt: f32, weight: FontWeight) -> f32 {
+    let width_mm = text_width_mm(text, preferred_size_pt, weight);
+
+    if width_mm <= max_width_mm {
+        preferred_size_pt
+    } else {
+        (preferred_size_pt * max_width_mm / width_mm).max(MIN_TEXT_SIZE_PT)
+    }
+}
+
+// Words longer than the line are left whole, as they are only shrunk until everything else fits.
+fn wrap_text(text: &str, max_width_mm: f32, size_pt: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let candidate = if line.is_empty() { word.to_owned() } else { format!("{line} {word}") };
+
+            if line.is_empty() || text_width_mm(&candidate, size_pt, FontWeight::Regular) <= max_width_mm {
+                line = candidate;
+            } else {
+                lines.push(core::mem::replace(&mut line, word.to_owned()));
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Mana symbols lose their braces, symbols of more than one character such as hybrid mana are kept apart in brackets.
+fn mana_cost_text(mana_cost: &str) -> String {
+    mana_cost.split(['{', '}'])
+        .filter(|symbol| !symbol.is_empty())
+        .map(|symbol| if symbol.chars().count() == 1 || symbol.chars().all(|character| character.is_ascii_digit()) {
+            symbol.to_owned()
+        } else {
+            format!("({symbol})")
+        })
+        .collect()
+}
+
+// Lays out a face in a box, with the name and cost on top, then the type line, the rules text and the stats in the corner.
+fn layout_face(text_card: &mut TextCard, face: &FaceText, frame: FrameRect) {
+    let left = frame.x_mm + PADDING_MM;
+    let right = frame.x_mm + frame.width_mm - PADDING_MM;
+    let bottom = frame.y_mm + frame.height_mm;
+    text_card.rects.push(frame);
+
+    let cost = mana_cost_text(&face.mana_cost);
+    let cost_width = text_width_mm(&cost, COST_SIZE_PT, FontWeight::Regular);
+    let name_gap = if cost.is_empty() { 0.0 } else { PADDING_MM };
+    let name_size = fit_line(&face.name, right - left - cost_width - name_gap, TITLE_SIZE_PT, FontWeight::Bold);
+
+    let title_baseline = frame.y_mm + PADDING_MM + TITLE_SIZE_PT * MM_PER_POINT * 0.8;
+    text_card.spans.push(TextSpan { text: face.name.clone(), x_mm: left, y_mm: title_baseline, size_pt: name_size, weight: FontWeight::Bold, anchor: TextAnchor::Start });
+    if !cost.is_empty() {
+        text_card.spans.push(TextSpan { text: cost, x_mm: right, y_mm: title_baseline, size_pt: COST_SIZE_PT, weight: FontWeight::Regular, anchor: TextAnchor::End });
+    }
+
+    let title_rule = title_baseline + PADDING_MM + 0.5;
+    text_card.lines.push(FrameLine { from_mm: (frame.x_mm, title_rule), to_mm: (frame.x_mm + frame.width_mm, title_rule) });
+
+    let type_size = fit_line(&face.type_line, right - left, TYPE_LINE_SIZE_PT, FontWeight::Regular);
+    let type_baseline = title_rule + PADDING_MM + TYPE_LINE_SIZE_PT * MM_PER_POINT * 0.75;
+    text_card.spans.push(TextSpan { text: face.type_line.clone(), x_mm: left, y_mm: type_baseline, size_pt: type_size, weight: FontWeight::Regular, anchor: TextAnchor::Start });
+
+    let type_rule = type_baseline + PADDING_MM;
+    text_card.lines.push(FrameLine { from_mm: (frame.x_mm, type_rule), to_mm: (frame.x_mm + frame.width_mm, type_rule) });
+
+    // The stats sit in a box in the bottom right corner, which the rules text stays above.
+    let mut text_bottom = bottom - PADDING_MM;
+    if let Some(stats) = &face.stats {
+        let stats_height = STATS_SIZE_PT * MM_PER_POINT + 2.0 * PADDING_MM;
+        let stats_width = text_width_mm(stats, STATS_SIZE_PT, FontWeight::Bold) + 4.0 * PADDING_MM;
+        let stats_top = bottom - stats_height;
+
+        text_card.rects.push(FrameRect { x_mm: right + PADDING_MM - stats_width, y_mm: stats_top, width_mm: stats_width, height_mm: stats_height });
+        text_card.spans.push(TextSpan {
+            text: stats.clone(),
+            x_mm: right - PADDING_MM,
+            y_mm: bottom - PADDING_MM - STATS_SIZE_PT * MM_PER_POINT * 0.15,
+            size_pt: STATS_SIZE_PT,
+            weight: FontWeight::Bold,
+            anchor: TextAnchor::End,
+        });
+        text_bottom = stats_top - PADDING_MM / 2.0;
+    }
+
+    // Shrinks the rules text until every line fits above the bottom of the box.
+    let text_top = type_rule + PADDING_MM;
+    let mut size_pt = RULES_TEXT_SIZE_PT;
+    let mut lines = wrap_text(&face.oracle_text, right - left, size_pt);
+    while size_pt > MIN_TEXT_SIZE_PT && lines.len() as f32 * size_pt * LINE_SPACING * MM_PER_POINT > text_bottom - text_top {
+        size_pt = (size_pt - 0.25).max(MIN_TEXT_SIZE_PT);
+        lines = wrap_text(&face.oracle_text, right - left, size_pt);
+    }
+
+    let line_height = size_pt * LINE_SPACING * MM_PER_POINT;
+    for (index, line) in lines.into_iter().enumerate().filter(|(_, line)| !line.is_empty()) {
+        let baseline = text_top + index as f32 * line_height + size_pt * MM_PER_POINT * 0.8;
+        text_card.spans.push(TextSpan { text: line, x_mm: left, y_mm: baseline, size_pt, weight: FontWeight::Regular, anchor: TextAnchor::Start });
+    }
+}
+
+// Faces that share a side, as on split, flip and adventure cards, each get an equal part of it from top to bottom.
+fn layout_side(faces: &[FaceText]) -> TextCard {
+    let mut text_card = TextCard::default();
+    let width = CARD_WIDTH_MM - 2.0 * MARGIN_MM;
+    let height = (CARD_HEIGHT_MM - 2.0 * MARGIN_MM - (faces.len().max(1) - 1) as f32 * MARGIN_MM) / faces.len().max(1) as f32;
+
+    for (index, face) in faces.iter().enumerate() {
+        let frame = FrameRect { x_mm: MARGIN_MM, y_mm: MARGIN_MM + index as f32 * (height + MARGIN_MM), width_mm: width, height_mm: height };
+        layout_face(&mut text_card, face, frame);
+    }
+
+    text_card
+}
+
+// Double-faced cards have a side for each face, like their images, other cards have all their faces on one side.
+pub fn text_proxy_sides(card: &Card) -> Vec<TextCard> {
+    match &card.card_faces {
+        Some(card_faces) if card.image_uris.is_none() && card_faces.len() > 1 => {
+            card_faces.iter().map(|face| layout_side(&[FaceText::from_face(face)])).collect()
+        },
+        Some(card_faces) if !card_faces.is_empty() => {
+            vec![layout_side(&card_faces.iter().map(FaceText::from_face).collect::<Vec<_>>())]
+        },
+        _ => vec![layout_side(&[FaceText::from_card(card)])],
+    }
+}
+
+pub fn write_svg(text_card: &TextCard) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CARD_WIDTH_MM}mm\" height=\"{CARD_HEIGHT_MM}mm\" viewBox=\"0 0 {CARD_WIDTH_MM} {CARD_HEIGHT_MM}\">\
+<rect width=\"{CARD_WIDTH_MM}\" height=\"{CARD_HEIGHT_MM}\" fill=\"#fff\"/><g fill=\"none\" stroke=\"#000\" stroke-width=\"{LINE_WIDTH_MM}\">"
+    );
+
+    for rect in &text_card.rects {
+        svg += &format!("<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"/>", rect.x_mm, rect.y_mm, rect.width_mm, rect.height_mm);
+    }
+    for line in &text_card.lines {
+        svg += &format!("<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\"/>", line.from_mm.0, line.from_mm.1, line.to_mm.0, line.to_mm.1);
+    }
+
+    svg += "</g><g font-family=\"Helvetica, Arial, sans-serif\" fill=\"#000\">";
+    for span in &text_card.spans {
+        svg += &format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\"{}{}>{}</text>",
+            span.x_mm,
+            span.y_mm,
+            span.size_pt * MM_PER_POINT,
+            if span.weight == FontWeight::Bold { " font-weight=\"bold\"" } else { "" },
+            if span.anchor == TextAnchor::End { " text-anchor=\"end\"" } else { "" },
+            xml::escape(&span.text),
+        );
+    }
+    svg += "</g></svg>";
+
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+    use crate::test_cards::test_card_json;
+
+    fn card_with(fields: serde_json::Value) -> Card {
+        let mut card = test_card_json(1, "Test Card", "TST", "1");
+        if let (Some(card), serde_json::Value::Object(fields)) = (card.as_object_mut(), fields) {
+            card.extend(fields);
+        }
+        serde_json::from_value(card).expect("Test card JSON should be a valid card")
+    }
+
+    fn span_texts(text_card: &TextCard) -> Vec<&str> {
+        text_card.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_creature_text_proxy() {
+        let card = card_with(json!({
+            "name": "Young Pyromancer",
+            "mana_cost": "{1}{R}",
+            "type_line": "Creature — Human Shaman",
+            "oracle_text": "Whenever you cast an instant or sorcery spell, create a 1/1 red Elemental creature token.",
+            "power": "2",
+            "toughness": "1",
+        }));
+        let sides = text_proxy_sides(&card);
+
+        assert_eq!(sides.len(), 1);
+        let texts = span_texts(&sides[0]);
+        assert_eq!(&texts[..4], ["Young Pyromancer", "1R", "Creature — Human Shaman", "2/1"]);
+        assert!(texts[4].starts_with("Whenever you cast"));
+        assert!(write_svg(&sides[0]).contains("<text x=\"57.00\" y=\"83.02\" font-size=\"3.17\" font-weight=\"bold\" text-anchor=\"end\">2/1</text>"));
+    }
+
+    #[test]
+    fn test_text_auto_fits() {
+        let card = card_with(json!({
+            "name": "Asmoranomardicadaistinaculdacar & Friends",
+            "mana_cost": "{W/U}{W/U}{2/B}",
+            "oracle_text": "Draw a card, then discard a card. ".repeat(30),
+            "defense": "5",
+        }));
+        let side = &text_proxy_sides(&card)[0];
+        let inner_right = CARD_WIDTH_MM - MARGIN_MM;
+
+        assert_eq!(side.spans[1].text, "(W/U)(W/U)(2/B)");
+        assert!(side.spans[0].size_pt < TITLE_SIZE_PT);
+        assert!(text_width_mm(&side.spans[0].text, side.spans[0].size_pt, FontWeight::Bold) + text_width_mm(&side.spans[1].text, COST_SIZE_PT, FontWeight::Regular) < inner_right - MARGIN_MM);
+
+        // The defence box is at the bottom, with every line of the shrunk rules text above it.
+        let stats_top = side.rects[1].y_mm;
+        let rules_text: Vec<&TextSpan> = side.spans.iter().filter(|span| span.text.starts_with("Draw")).collect();
+        assert!(rules_text[0].size_pt < RULES_TEXT_SIZE_PT);
+        assert!(rules_text.iter().all(|span| span.y_mm < stats_top && text_width_mm(&span.text, span.size_pt, FontWeight::Regular) < inner_right - MARGIN_MM));
+    }
+
+    #[test]
+    fn test_multiple_face_layouts() {
+        let face = |name: &str, mana_cost: &str, type_line: &str| json!({"object": "card_face", "name": name, "mana_cost": mana_cost, "type_line": type_line, "oracle_text": "Draw a card."});
+
+        let split = card_with(json!({"layout": "split", "card_faces": [face("Fire", "{1}{R}", "Instant"), face("Ice", "{1}{U}", "Instant")]}));
+        let split_sides = text_proxy_sides(&split);
+        assert_eq!(split_sides.len(), 1);
+        assert_eq!(split_sides[0].rects.len(), 2);
+        assert!(split_sides[0].rects[0].y_mm + split_sides[0].rects[0].height_mm < split_sides[0].rects[1].y_mm);
+        assert_eq!(span_texts(&split_sides[0]), ["Fire", "1R", "Instant", "Draw a card.", "Ice", "1U", "Instant", "Draw a card."]);
+
+        // Double-faced cards have their images on the faces, and a side for each face.
+        let mut transform_json = test_card_json(2, "Delver of Secrets // Insectile Aberration", "ISD", "51");
+        let image_uris = transform_json["image_uris"].take();
+        let mut front = face("Delver of Secrets", "{U}", "Creature — Human Wizard");
+        let mut back = face("Insectile Aberration", "", "Creature — Human Insect");
+        front["image_uris"] = image_uris.clone();
+        back["image_uris"] = image_uris;
+        back["power"] = json!("3");
+        back["toughness"] = json!("2");
+        transform_json["card_faces"] = json!([front, back]);
+        if let Some(card) = transform_json.as_object_mut() {
+            card.remove("image_uris");
+        }
+
+        let transform: Card = serde_json::from_value(transform_json).expect("Test card JSON should be a valid card");
+        let transform_sides = text_proxy_sides(&transform);
+        assert_eq!(transform_sides.len(), 2);
+        assert_eq!(span_texts(&transform_sides[1]), ["Insectile Aberration", "Creature — Human Insect", "3/2", "Draw a card."]);
+    }
+}
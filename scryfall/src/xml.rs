@@ -0,0 +1,138 @@
+use alloc::{borrow::Cow, format, string::String};
+
+// Just enough XML for deck files, which are lists of elements with attributes, grouped at most once.
+// Comments, CDATA and elements nested in another element of the same name are not supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct XmlElement<'a> {
+    document: &'a str,
+    // Byte offset of the element in the document.
+    offset: usize,
+    // The whole element as written in the document.
+    pub source: &'a str,
+    attributes: &'a str,
+    content_offset: usize,
+    // None for self-closing elements.
+    pub content: Option<&'a str>,
+}
+
+impl<'a> XmlElement<'a> {
+    pub fn attribute(&self, name: &str) -> Option<Cow<'a, str>> {
+        let mut rest = self.attributes;
+
+        loop {
+            let (attribute_name, after_name) = rest.trim_start().split_once('=')?;
+            let after_name = after_name.trim_start();
+            let quote = after_name.chars().next().filter(|quote| *quote == '"' || *quote == '\'')?;
+            let (value, after_value) = after_name[1..].split_once(quote)?;
+
+            if attribute_name.trim() == name {
+                return Some(unescape(value));
+            }
+            rest = after_value;
+        }
+    }
+
+    pub fn line_number(&self) -> usize {
+        self.document[..self.offset].matches('\n').count() + 1
+    }
+
+    // Finds the elements with the given name inside this one.
+    pub fn children(&self, name: &'a str) -> impl Iterator<Item = XmlElement<'a>> + 'a {
+        let content_length = self.content.map_or(0, str::len);
+        elements_between(self.document, self.content_offset, self.content_offset + content_length, name)
+    }
+}
+
+// Finds every element with the given name, in document order.
+pub(crate) fn elements<'a>(document: &'a str, name: &'a str) -> impl Iterator<Item = XmlElement<'a>> + 'a {
+    elements_between(document, 0, document.len(), name)
+}
+
+fn elements_between<'a>(document: &'a str, mut position: usize, end: usize, name: &'a str) -> impl Iterator<Item = XmlElement<'a>> + 'a {
+    core::iter::from_fn(move || loop {
+        let start = position + document[position..end].find('<')?;
+        let after_name = document[start + 1..end].strip_prefix(name);
+        position = start + 1;
+
+        // Other elements whose names start with this one, such as <Cardsets> when looking for <Cards>.
+        let Some(after_name) = after_name.filter(|after_name| after_name.starts_with(|character: char| character.is_whitespace() || character == '/' || character == '>')) else {
+            continue;
+        };
+
+        let tag_end = after_name.find('>')?;
+        let tag_start = end - after_name.len();
+        position = tag_start + tag_end + 1;
+
+        if let Some(attributes) = after_name[..tag_end].strip_suffix('/') {
+            return Some(XmlElement { document, offset: start, source: &document[start..position], attributes, content_offset: position, content: None });
+        }
+
+        let closing_tag = format!("</{name}>");
+        let content_offset = position;
+        let content_length = document[position..end].find(&closing_tag)?;
+        let content = &document[position..position + content_length];
+        position += content_length + closing_tag.len();
+
+        return Some(XmlElement { document, offset: start, source: &document[start..position], attributes: &after_name[..tag_end], content_offset, content: Some(content) });
+    })
+}
+
+pub(crate) fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(entity_start) = rest.find('&') {
+        unescaped.push_str(&rest[..entity_start]);
+        rest = &rest[entity_start..];
+
+        let entity = rest[1..].split_once(';').map(|(entity, _)| entity);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity.strip_prefix("#x").map_or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()), |hexadecimal| u32::from_str_radix(hexadecimal, 16).ok())
+                .and_then(char::from_u32),
+        });
+
+        // Unknown entities are kept as they are.
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                unescaped.push(character);
+                rest = &rest[entity.len() + 2..];
+            },
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+
+    unescaped.push_str(rest);
+    Cow::Owned(unescaped)
+}
+
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
@@ -1,6 +1,7 @@
 import init, {generate_proxies_from_textbox, generate_proxies_from_file_contents, get_printings_for_card} from './pkg/wasm_proxies.js';
 
 let imageBlobUrls = [];
+let generatedDeck = null;
 
 function getCustomCards() {
     for (const blobUrl of imageBlobUrls) {
@@ -19,8 +20,8 @@ function getCustomCards() {
 async function proxiesTxtButtonClicked() {
     document.getElementById("loading-overlay").style.display = "block";
     await generate_proxies_from_textbox(getCustomCards(), document.getElementById("deck-diff").checked, cardClickedWrapper)
-        .then(() => {
-            updatePrintButton();
+        .then((generated_deck) => {
+            setGeneratedDeck(generated_deck);
         })
         .catch((error) => {
             console.error(error);
@@ -44,9 +45,9 @@ function proxiesFileButtonClicked() {
                 if (old_file) {
                     const old_reader = new FileReader();
                     old_reader.onload = async () => {
-                        await generate_proxies_from_file_contents(reader.result, file.type, old_reader.result, old_file.type, getCustomCards(), cardClickedWrapper)
-                            .then(() => {
-                                updatePrintButton();
+                        await generate_proxies_from_file_contents(reader.result, old_reader.result, getCustomCards(), cardClickedWrapper)
+                            .then((generated_deck) => {
+                                setGeneratedDeck(generated_deck);
                             })
                             .catch((error) => {
                                 console.error(error);
@@ -59,9 +60,9 @@ function proxiesFileButtonClicked() {
                     old_reader.readAsText(old_file);
                 }
             } else {
-                await generate_proxies_from_file_contents(reader.result, file.type, null, null, getCustomCards(), cardClickedWrapper)
-                    .then(() => {
-                        updatePrintButton();
+                await generate_proxies_from_file_contents(reader.result, null, getCustomCards(), cardClickedWrapper)
+                    .then((generated_deck) => {
+                        setGeneratedDeck(generated_deck);
                     })
                     .catch((error) => {
                         console.error(error);
@@ -228,9 +229,9 @@ async function cardClicked(image_urls, prints_search_uri, card_name, is_custom_c
         const current_printing_node = printing_nodes[index];
         const right_printing_node = printing_nodes[right_index];
 
-        const left_printing = printings.printings[left_index].faces;
-        const current_printing = printings.printings[index].faces;
-        const right_printing = printings.printings[right_index].faces;
+        const left_printing = printings.printings[left_index];
+        const current_printing = printings.printings[index];
+        const right_printing = printings.printings[right_index];
         
         left_button_nodes[index].onclick = () => {
             for (const card_face_node of card_faces_nodes[left_left_index]) {
@@ -239,6 +240,7 @@ async function cardClicked(image_urls, prints_search_uri, card_name, is_custom_c
 
             current_printing_node.style.display = "none";
             changePrinting(current_printing, left_printing, prints_search_uri, card_name);
+            generatedDeck?.replace_printing(current_printing, left_printing);
             left_printing_node.style.display = "";
         };
 
@@ -249,6 +251,7 @@ async function cardClicked(image_urls, prints_search_uri, card_name, is_custom_c
 
             current_printing_node.style.display = "none";
             changePrinting(current_printing, right_printing, prints_search_uri, card_name);
+            generatedDeck?.replace_printing(current_printing, right_printing);
             right_printing_node.style.display = "";
         };
 
@@ -264,18 +267,58 @@ async function cardClicked(image_urls, prints_search_uri, card_name, is_custom_c
     card_printings_overlay.replaceChildren(...printing_nodes);
 }
 
-function changePrinting(old_printing_urls, new_printing_urls, prints_search_uri, card_name) {
-    for (const [old_printing, new_printing] of old_printing_urls.map((new_url, index) => [new_url, new_printing_urls[index]])) {
-        let cards = document.getElementById("proxies").children;
+function changePrinting(old_printing, new_printing, prints_search_uri, card_name) {
+    for (const [old_printing_url, new_printing_url] of old_printing.faces.map((old_url, index) => [old_url, new_printing.faces[index]])) {
+        let cards = document.getElementById("proxies").querySelectorAll(".card-face");
         for (const card of cards) {
-            if (card.className === "card-face" && card.src === old_printing) {
-                card.src = new_printing;
-                card.onclick = cardClicked.bind(card, new_printing_urls, prints_search_uri, card_name, false);
+            if (card.src === old_printing_url) {
+                card.src = new_printing_url;
+                card.onclick = cardClicked.bind(card, new_printing.faces, prints_search_uri, card_name, false);
             }
         }
     }
 }
 
+function exportDeckButtonClicked() {
+    const format = document.querySelector("input[name='export-format']:checked").value;
+
+    let deck_data;
+    try {
+        deck_data = generatedDeck.export(format);
+    } catch (error) {
+        console.error(error);
+        window.alert(error);
+        return;
+    }
+
+    const blob_url = URL.createObjectURL(new Blob([deck_data], { type: format === "csv" ? "text/csv" : "text/plain" }));
+    const link = document.createElement("a");
+    link.href = blob_url;
+    link.download = format === "csv" ? "deck.csv" : "deck.txt";
+    link.click();
+    URL.revokeObjectURL(blob_url);
+}
+
+function deckListDiagnosticClicked(event) {
+    const diagnostic = event.target.closest(".deck-list-diagnostic");
+    if (!diagnostic) {
+        return;
+    }
+
+    // Diagnostics for uploaded files have no textbox to select the line in.
+    const deck_list = document.getElementById(diagnostic.dataset.deckList);
+    if (!(deck_list instanceof HTMLTextAreaElement)) {
+        return;
+    }
+
+    const lines = deck_list.value.split("\n");
+    const line_index = Number(diagnostic.dataset.line) - 1;
+    const line_start = lines.slice(0, line_index).reduce((length, line) => length + line.length + 1, 0);
+
+    deck_list.focus();
+    deck_list.setSelectionRange(line_start, line_start + lines[line_index].length);
+}
+
 function clearUploadedCustomCardsClicked(update_file_selection_text_callback) {
     const custom_cards_upload = document.getElementById("custom-cards-upload");
     custom_cards_upload.value = null;
@@ -315,13 +358,19 @@ function switchTab(selected, other, selected_controls, other_controls) {
     selected_controls.style.display = "";
 }
 
+function setGeneratedDeck(generated_deck) {
+    generatedDeck?.free();
+    generatedDeck = generated_deck;
+    updatePrintButton();
+}
+
 function updatePrintButton() {
-    if (document.getElementById("proxies").hasChildNodes()) {
-        document.getElementById("proxies-txt-print-button").disabled = false;
-        document.getElementById("proxies-file-print-button").disabled = false;
-    } else {
-        document.getElementById("proxies-txt-print-button").disabled = true;
-        document.getElementById("proxies-file-print-button").disabled = true;
+    const no_proxies = !document.getElementById("proxies").hasChildNodes();
+    for (const button_id of ["proxies-txt-print-button", "proxies-file-print-button"]) {
+        document.getElementById(button_id).disabled = no_proxies;
+    }
+    for (const button_id of ["proxies-txt-export-button", "proxies-file-export-button"]) {
+        document.getElementById(button_id).disabled = generatedDeck === null;
     }
 }
 
@@ -337,7 +386,11 @@ document.getElementById("proxies-file-button").addEventListener("click", proxies
 document.getElementById("proxies-txt-print-button").addEventListener("click", () => window.print());
 document.getElementById("proxies-file-print-button").addEventListener("click", () => window.print());
 
+document.getElementById("proxies-txt-export-button").addEventListener("click", exportDeckButtonClicked);
+document.getElementById("proxies-file-export-button").addEventListener("click", exportDeckButtonClicked);
+
 document.getElementById("deck-diff").addEventListener("change", toggleDeckDiff);
+document.addEventListener("click", deckListDiagnosticClicked);
 
 document.getElementById("proxies-file-select-wrapper").addEventListener("click", () => document.getElementById("proxies-file-select").click());
 document.getElementById("old-proxies-file-select-wrapper").addEventListener("click", () => document.getElementById("old-proxies-file-select").click());
@@ -1,14 +1,16 @@
 use alloc::{borrow::ToOwned, format, string::{String, ToString}, vec::Vec};
-use scryfall::{api_interface::{wasm_fetch_wrapper::WasmFetchWrapper, ApiInterface}, card_images_helper::extract_images, deck_diff::deck_diff, deck_parsers::{parse_json_data, parse_txt_data_js}};
+use scryfall::{api_interface::{wasm_fetch_wrapper::WasmFetchWrapper, ApiInterface}, card_images_helper::{border_rgb, extract_images}, deck_diff::deck_diff, deck_list::{DeckList, ParseDiagnostic}, deck_parsers::{detect_format, parse_deck_data, parse_txt_data}, deck_writers::{write_arena_txt, write_csv}};
 use wasm_bindgen::prelude::*;
-use web_sys::{js_sys::{Array, Function, JsString}, window, Document, HtmlDivElement, HtmlImageElement, HtmlTextAreaElement};
+use web_sys::{js_sys::{Array, Function, JsString}, window, Document, Element, HtmlDivElement, HtmlImageElement, HtmlTextAreaElement};
 
-use scryfall::fetch_card_data::fetch_deck::FetchDeck;
-use crate::{rust_error_to_js, user_options::{get_selected_options, UserOptions}};
+use scryfall::fetch_card_data::fetch_deck::{FetchDeck, ResolvedDeck};
+use crate::{printings::Printing, rust_error_to_js, user_options::{get_selected_options, UserOptions}};
 
 const DECK_LIST_TEXTBOX_ID: &str = "deck-list";
 const OLD_DECK_LIST_TEXTBOX_ID: &str = "old-deck-list";
 const PROXIES_DIV_ID: &str = "proxies";
+const DECK_FILE_SELECT_ID: &str = "proxies-file-select-wrapper";
+const OLD_DECK_FILE_SELECT_ID: &str = "old-proxies-file-select-wrapper";
 
 #[wasm_bindgen]
 pub struct CardClickedData {
@@ -21,12 +23,36 @@ pub struct CardClickedData {
     pub is_custom_card: bool,
 }
 
-async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &Document, card_click_callback: Function) -> Result<(), JsValue> {
+// The deck the proxies were generated from, kept with its sections so it can be exported with the printings picked on the page.
+#[wasm_bindgen]
+pub struct GeneratedDeck {
+    resolved_deck: ResolvedDeck,
+}
+
+#[wasm_bindgen]
+impl GeneratedDeck {
+    pub fn replace_printing(&mut self, old_printing: &Printing, new_printing: &Printing) {
+        self.resolved_deck.replace_printing(old_printing.card.id, &new_printing.card);
+    }
+
+    pub fn export(&self, format: &str) -> Result<String, JsValue> {
+        let ResolvedDeck { main_deck, sideboard, .. } = &self.resolved_deck;
+
+        match format {
+            "arena" => Ok(write_arena_txt(main_deck, sideboard)),
+            "csv" => Ok(write_csv(main_deck, sideboard)),
+            _ => Err(format!("Unknown deck export format {format}").into()),
+        }
+    }
+}
+
+async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &Document, card_click_callback: Function) -> Result<GeneratedDeck, JsValue> {
     let interface = ApiInterface::<WasmFetchWrapper>::new()
         .map_err(rust_error_to_js)?;
 
-    let deck_cards = interface.fetch_deck(&user_options.deck_list, user_options.include_tokens).await
+    let resolved_deck = interface.fetch_deck_sections(&user_options.deck_list, user_options.include_tokens).await
         .map_err(rust_error_to_js)?;
+    let deck_cards = resolved_deck.cards();
 
     let mut cards_to_display = if let Some(old_deck) = user_options.old_deck {
         let old_deck_cards = interface.fetch_deck(&old_deck, user_options.include_tokens).await
@@ -53,6 +79,16 @@ async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &D
     };
     proxies_section.set_text_content(None);
 
+    let mut guide_classes = Vec::new();
+    if user_options.crop_marks {
+        guide_classes.push("crop-marks");
+    }
+    if user_options.cut_lines {
+        guide_classes.push("cut-lines");
+    }
+    proxies_section.set_class_name(&guide_classes.join(" "));
+    proxies_section.set_attribute("style", &format!("--bleed: {}mm", user_options.bleed_mm))?;
+
     for extra_card in user_options.extra_cards {
         let image_node = document.create_element("img")?.dyn_into::<HtmlImageElement>()?;
         image_node.set_src(&extra_card);
@@ -61,6 +97,11 @@ async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &D
         image_node.set_alt("Custom card");
         image_node.set_attribute("loading", "lazy")?;
 
+        // Custom cards have no known border colour, black is the most common.
+        if user_options.bleed_mm > 0.0 {
+            image_node.set_attribute("style", "background-color: rgb(0, 0, 0)")?;
+        }
+
         let card_face_images_array = Array::of1(&JsString::from(extra_card));
         image_node.set_onclick(Some(&card_click_callback.bind1(&image_node, &JsValue::from(CardClickedData {
             card_face_images_array,
@@ -68,10 +109,16 @@ async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &D
             card_name: "".to_owned(),
             is_custom_card: true,
         }))));
-        proxies_section.append_child(&image_node)?;
+
+        let wrapper_node = document.create_element("span")?;
+        wrapper_node.set_class_name("card-wrapper");
+        wrapper_node.append_child(&image_node)?;
+        proxies_section.append_child(&wrapper_node)?;
     }
     
     for (card, card_face_images) in card_images {
+        let [red, green, blue] = border_rgb(&card);
+
         for card_image in &card_face_images {
             let image_node = document.create_element("img")?.dyn_into::<HtmlImageElement>()?;
             image_node.set_src(card_image);
@@ -80,6 +127,11 @@ async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &D
             image_node.set_alt(&card.name);
             image_node.set_attribute("loading", "lazy")?;
 
+            if user_options.bleed_mm > 0.0 {
+                image_node.set_attribute("style", &format!("background-color: rgb({red}, {green}, {blue})"))?;
+            }
+
+
             let card_face_images_array = Array::from_iter(card_face_images.iter().cloned().map(JsString::from));
             image_node.set_onclick(Some(&card_click_callback.bind1(&image_node, &JsValue::from(CardClickedData {
                 card_face_images_array,
@@ -87,16 +139,57 @@ async fn add_proxy_images_from_deck_list(user_options: UserOptions, document: &D
                 card_name: card.name.clone(),
                 is_custom_card: false,
             }))));
-            
-            proxies_section.append_child(&image_node)?;
+
+            let wrapper_node = document.create_element("span")?;
+            wrapper_node.set_class_name("card-wrapper");
+            wrapper_node.append_child(&image_node)?;
+            proxies_section.append_child(&wrapper_node)?;
         }
     }
 
+    Ok(GeneratedDeck { resolved_deck })
+}
+
+// Lists the parse diagnostics right after the deck list they belong to, replacing the ones from the previous run.
+// Each item keeps the line number and the id of the deck list, so the page can select the line when it is clicked.
+fn show_diagnostics(document: &Document, deck_list_element: &Element, diagnostics: &[ParseDiagnostic]) -> Result<(), JsValue> {
+    let list_id = format!("{}-diagnostics", deck_list_element.id());
+
+    let list_node = match document.get_element_by_id(&list_id) {
+        Some(list_node) => list_node,
+        None => {
+            let list_node = document.create_element("ul")?;
+            list_node.set_id(&list_id);
+            list_node.set_class_name("deck-list-diagnostics");
+            deck_list_element.after_with_node_1(&list_node)?;
+            list_node
+        },
+    };
+    list_node.set_text_content(None);
+    deck_list_element.class_list().toggle_with_force("has-diagnostics", !diagnostics.is_empty())?;
+
+    for diagnostic in diagnostics {
+        let item_node = document.create_element("li")?;
+        item_node.set_class_name(if diagnostic.reason.skips_line() { "deck-list-diagnostic skipped" } else { "deck-list-diagnostic" });
+        item_node.set_text_content(Some(&diagnostic.to_string()));
+        item_node.set_attribute("data-deck-list", &deck_list_element.id())?;
+        item_node.set_attribute("data-line", &diagnostic.line_number.to_string())?;
+        list_node.append_child(&item_node)?;
+    }
+
     Ok(())
 }
 
+fn show_diagnostics_for_element(document: &Document, element_id: &str, deck_list: &DeckList) -> Result<(), JsValue> {
+    let Some(deck_list_element) = document.get_element_by_id(element_id) else {
+        return Err(format!("Could not find element {element_id}").into());
+    };
+
+    show_diagnostics(document, &deck_list_element, &deck_list.diagnostics)
+}
+
 #[wasm_bindgen]
-pub async fn generate_proxies_from_textbox(custom_card_blob_urls: Array, old_deck_list_enabled: JsValue, card_click_callback: Function) -> Result<(), JsValue> {
+pub async fn generate_proxies_from_textbox(custom_card_blob_urls: Array, old_deck_list_enabled: JsValue, card_click_callback: Function) -> Result<GeneratedDeck, JsValue> {
     let mut custom_cards: Vec<String> = Vec::new();
     
     for card in custom_card_blob_urls.into_iter() {
@@ -116,16 +209,19 @@ pub async fn generate_proxies_from_textbox(custom_card_blob_urls: Array, old_dec
         return Err("Could not find deck list textbox".into());
     };
 
-    let deck_list_text = deck_list_textbox.dyn_into::<HtmlTextAreaElement>()?.value();
-    let deck_list = parse_txt_data_js(&deck_list_text)?;
+    let deck_list_text = deck_list_textbox.clone().dyn_into::<HtmlTextAreaElement>()?.value();
+    let deck_list = parse_txt_data(&deck_list_text);
+    show_diagnostics(&document, &deck_list_textbox, &deck_list.diagnostics)?;
 
     let old_deck_list = if old_deck_list_enabled.is_truthy() {
         let Some(old_deck_list_textbox) = document.get_element_by_id(OLD_DECK_LIST_TEXTBOX_ID) else {
             return Err("Could not find old deck list textbox".into());
         };
 
-        let old_deck_list_text = old_deck_list_textbox.dyn_into::<HtmlTextAreaElement>()?.value();
-        Some(parse_txt_data_js(&old_deck_list_text)?)
+        let old_deck_list_text = old_deck_list_textbox.clone().dyn_into::<HtmlTextAreaElement>()?.value();
+        let old_deck_list = parse_txt_data(&old_deck_list_text);
+        show_diagnostics(&document, &old_deck_list_textbox, &old_deck_list.diagnostics)?;
+        Some(old_deck_list)
     } else {
         None
     };
@@ -133,8 +229,9 @@ pub async fn generate_proxies_from_textbox(custom_card_blob_urls: Array, old_dec
     add_proxy_images_from_deck_list(get_selected_options(deck_list, old_deck_list, custom_cards, &document)?, &document, card_click_callback).await
 }
 
+// Browsers only know the MIME types of some deck formats, so the format is recognised from the contents instead.
 #[wasm_bindgen]
-pub async fn generate_proxies_from_file_contents(file_contents: JsValue, file_mime_type: JsValue, old_file_contents: JsValue, old_file_mime_type: JsValue, custom_card_blob_urls: Array, card_click_callback: Function) -> Result<(), JsValue> {
+pub async fn generate_proxies_from_file_contents(file_contents: JsValue, old_file_contents: JsValue, custom_card_blob_urls: Array, card_click_callback: Function) -> Result<GeneratedDeck, JsValue> {
     let mut custom_cards: Vec<String> = Vec::new();
     
     for card in custom_card_blob_urls.into_iter() {
@@ -154,15 +251,9 @@ pub async fn generate_proxies_from_file_contents(file_contents: JsValue, file_mi
     let Some(contents) = file_contents.as_string() else {
         return Err("File contents must be a string".into());
     };
-    let Some(file_type) = file_mime_type.as_string() else {
-        return Err("File MIME type must be a string".into());
-    };
 
-    let deck_list = match file_type.as_str() {
-        "text/plain" | "" => parse_txt_data_js(&contents)?,
-        "application/json" => parse_json_data(&contents).map_err(rust_error_to_js)?,
-        _ => return Err(format!("Unsupported MIME type {file_type}").into()),
-    };
+    let deck_list = parse_deck_data(&contents, detect_format(&contents)).map_err(rust_error_to_js)?;
+    show_diagnostics_for_element(&document, DECK_FILE_SELECT_ID, &deck_list)?;
 
     let old_deck_list = if old_file_contents.is_null() {
         None
@@ -170,16 +261,11 @@ pub async fn generate_proxies_from_file_contents(file_contents: JsValue, file_mi
         let Some(old_contents) = old_file_contents.as_string() else {
             return Err("File contents must be a string".into());
         };
-        let Some(old_file_type) = old_file_mime_type.as_string() else {
-            return Err("File MIME type must be a string".into());
-        };
 
-        Some(match old_file_type.as_str() {
-            "text/plain" | "" => parse_txt_data_js(&old_contents)?,
-            "application/json" => parse_json_data(&old_contents).map_err(rust_error_to_js)?,
-            _ => return Err(format!("Unsupported MIME type {old_file_type}").into()),
-        })
+        let old_deck_list = parse_deck_data(&old_contents, detect_format(&old_contents)).map_err(rust_error_to_js)?;
+        show_diagnostics_for_element(&document, OLD_DECK_FILE_SELECT_ID, &old_deck_list)?;
+        Some(old_deck_list)
     };
 
     add_proxy_images_from_deck_list(get_selected_options(deck_list, old_deck_list, custom_cards, &document)?, &document, card_click_callback).await
-}
\ No newline at end of file
+}
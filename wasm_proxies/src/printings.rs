@@ -14,6 +14,7 @@ pub struct Printings {
 
 #[wasm_bindgen]
 pub struct Printing {
+    pub(crate) card: Card,
     #[wasm_bindgen(getter_with_clone)]
     pub faces: Array,
     #[wasm_bindgen(getter_with_clone)]
@@ -31,9 +32,10 @@ fn printings_vec_to_array(card_printing_images: Vec<(Card, Vec<String>)>) -> Arr
                 faces: Array::from_iter(
                     printing_images.into_iter().map(JsString::from)
                 ),
-                set: card.set_name,
-                collector_number: card.collector_number,
+                set: card.set_name.clone(),
+                collector_number: card.collector_number.clone(),
                 scryfall_url: card.scryfall_uri.to_string(),
+                card,
             })
         )
     )
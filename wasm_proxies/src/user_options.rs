@@ -1,12 +1,14 @@
 use alloc::{string::String, vec::Vec};
-use hashbrown::HashMap;
-use scryfall::{api_interface::collection_card_identifier::CollectionCardIdentifier, card_images_helper::ImageUriType};
+use scryfall::{card_images_helper::ImageUriType, deck_list::DeckList};
 use wasm_bindgen::prelude::*;
 use web_sys::{Document, HtmlInputElement};
 
 const INCLUDE_BASIC_LANDS_CHECKBOX_ID: &str = "include-basic-lands";
 const INCLUDE_TOKENS_CHECKBOX_ID: &str = "include-tokens";
 const DECK_DIFF_CHECKBOX_ID: &str = "deck-diff";
+const CROP_MARKS_CHECKBOX_ID: &str = "crop-marks";
+const CUT_LINES_CHECKBOX_ID: &str = "cut-lines";
+const BLEED_INPUT_ID: &str = "bleed";
 
 const IMAGE_TYPE_SMALL_RADIO: &str = "image-type-small-radio";
 const IMAGE_TYPE_NORMAL_RADIO: &str = "image-type-normal-radio";
@@ -18,9 +20,12 @@ pub struct UserOptions {
     pub exclude_basic_lands: bool,
     pub include_tokens: bool,
     pub image_type: ImageUriType,
+    pub crop_marks: bool,
+    pub cut_lines: bool,
+    pub bleed_mm: f64,
     pub extra_cards: Vec<String>,
-    pub deck_list: HashMap<CollectionCardIdentifier, usize>,
-    pub old_deck: Option<HashMap<CollectionCardIdentifier, usize>>,
+    pub deck_list: DeckList,
+    pub old_deck: Option<DeckList>,
 }
 
 pub fn get_selected_image_type(document: &Document) -> Result<ImageUriType, JsValue> {
@@ -72,7 +77,7 @@ pub fn get_selected_image_type(document: &Document) -> Result<ImageUriType, JsVa
     Err("Could not find any checked image type radio button".into())
 }
 
-pub fn get_selected_options(deck_list: HashMap<CollectionCardIdentifier, usize>, old_deck_list: Option<HashMap<CollectionCardIdentifier, usize>>, custom_card_blob_urls: Vec<String>, document: &Document) -> Result<UserOptions, JsValue> {
+pub fn get_selected_options(deck_list: DeckList, old_deck_list: Option<DeckList>, custom_card_blob_urls: Vec<String>, document: &Document) -> Result<UserOptions, JsValue> {
     let include_basic_lands_checkbox = match document.get_element_by_id(INCLUDE_BASIC_LANDS_CHECKBOX_ID) {
         Some(include_basic_lands_checkbox) => include_basic_lands_checkbox.dyn_into::<HtmlInputElement>()?,
         None => return Err("Could not find include basic lands checkbox element".into()),
@@ -88,6 +93,25 @@ pub fn get_selected_options(deck_list: HashMap<CollectionCardIdentifier, usize>,
         None => return Err("Could not find deck diff checkbox element".into()),
     };
 
+    let crop_marks_checkbox = match document.get_element_by_id(CROP_MARKS_CHECKBOX_ID) {
+        Some(crop_marks_checkbox) => crop_marks_checkbox.dyn_into::<HtmlInputElement>()?,
+        None => return Err("Could not find crop marks checkbox element".into()),
+    };
+
+    let cut_lines_checkbox = match document.get_element_by_id(CUT_LINES_CHECKBOX_ID) {
+        Some(cut_lines_checkbox) => cut_lines_checkbox.dyn_into::<HtmlInputElement>()?,
+        None => return Err("Could not find cut lines checkbox element".into()),
+    };
+
+    let bleed_input = match document.get_element_by_id(BLEED_INPUT_ID) {
+        Some(bleed_input) => bleed_input.dyn_into::<HtmlInputElement>()?,
+        None => return Err("Could not find bleed input element".into()),
+    };
+
+    // An empty or invalid input is treated as no bleed.
+    let bleed_mm = bleed_input.value_as_number();
+    let bleed_mm = if bleed_mm.is_finite() && bleed_mm > 0.0 { bleed_mm } else { 0.0 };
+
     let old_deck = if deck_diff_checkbox.checked() {
         old_deck_list
     } else {
@@ -98,6 +122,9 @@ pub fn get_selected_options(deck_list: HashMap<CollectionCardIdentifier, usize>,
         exclude_basic_lands: !include_basic_lands_checkbox.checked(),
         include_tokens: include_tokens_checkbox.checked(),
         image_type: get_selected_image_type(document)?,
+        crop_marks: crop_marks_checkbox.checked(),
+        cut_lines: cut_lines_checkbox.checked(),
+        bleed_mm,
         extra_cards: custom_card_blob_urls,
         deck_list,
         old_deck,